@@ -1,6 +1,12 @@
 pub mod camera;
+pub mod motion;
+pub mod obstacles;
+pub mod vision;
 pub mod walls;
 
+pub use motion::Motion;
+pub use vision::Vision;
+
 use std::time::Duration;
 
 use crate::prelude::*;
@@ -15,6 +21,19 @@ pub struct KeyboardControlled;
 #[shrinkwrap(mutable)]
 pub struct Velocity(pub Vec3);
 
+/// Predators which hunt together. Actors pick their team when they join the
+/// game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Team(pub u8);
+
+/// Stamina drains while an entity exerts itself and regenerates while it
+/// rests.
+#[derive(Clone, Copy)]
+pub struct Stamina {
+    current: f32,
+    max: f32,
+}
+
 impl From<Vec3> for Velocity {
     fn from(v: Vec3) -> Self {
         Self(v)
@@ -52,3 +71,53 @@ impl Velocity {
         self.0 = direction * speed_clamp(speed);
     }
 }
+
+impl Team {
+    /// Each team has its own color which tints the sprites of its predators.
+    pub fn color(self) -> Color {
+        const COLORS: [(f32, f32, f32); 4] = [
+            (1.0, 1.0, 1.0),
+            (0.9, 0.3, 0.3),
+            (0.3, 0.5, 0.9),
+            (0.3, 0.8, 0.3),
+        ];
+        let (r, g, b) = COLORS[self.0 as usize % COLORS.len()];
+        Color::rgb(r, g, b)
+    }
+}
+
+impl Stamina {
+    /// Creates a new stamina pool which is full.
+    pub fn full(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Returns how much stamina is left.
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// Returns how much stamina is left as a fraction of the max.
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            self.current / self.max
+        } else {
+            0.0
+        }
+    }
+
+    /// Drains the stamina, but never below zero.
+    pub fn drain(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+
+    /// Regenerates the stamina, but never above the max.
+    pub fn regenerate(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Fills the stamina pool.
+    pub fn refill(&mut self) {
+        self.current = self.max;
+    }
+}
@@ -0,0 +1,112 @@
+use super::Velocity;
+use crate::{params::Species, prelude::*};
+
+/// Describes how an entity moves. Entities cannot change their velocity
+/// immediately: they accelerate and turn at limited rates, and drag slows
+/// them down while they coast.
+#[derive(Clone, Copy, Debug)]
+pub struct Motion {
+    /// Max change of speed per second.
+    pub max_acceleration: f32,
+    /// Max change of heading in radians per second.
+    pub max_turn_rate: f32,
+    /// Fraction of velocity lost each second.
+    pub drag: f32,
+}
+
+impl Motion {
+    /// Motion model of predators.
+    pub fn predator() -> Self {
+        use conf::predator::motion::*;
+        Self {
+            max_acceleration: MAX_ACCELERATION,
+            max_turn_rate: MAX_TURN_RATE,
+            drag: DRAG,
+        }
+    }
+
+    /// Motion model of prey of given species. Flocking steers the prey with
+    /// forces bounded by the same max acceleration and turns it at most as
+    /// fast as the max turn rate.
+    pub fn prey(species: &Species) -> Self {
+        use conf::prey::motion::*;
+        Self {
+            max_acceleration: species.max_steering_force,
+            max_turn_rate: MAX_TURN_RATE,
+            drag: DRAG,
+        }
+    }
+
+    /// Turns and accelerates towards target velocity as fast as the model
+    /// allows.
+    pub fn approach(
+        &self,
+        vel: &mut Velocity,
+        target: Vec3,
+        max_speed: f32,
+        delta_seconds: f32,
+    ) {
+        // An entity at rest can start moving in any direction.
+        let heading = if !vel.is_zero() {
+            vel.normalize()
+        } else if !target.is_zero() {
+            target.normalize()
+        } else {
+            return;
+        };
+        let turn = if target.is_zero() {
+            0.0
+        } else {
+            heading.signed_angle(target)
+        };
+
+        self.turn_and_accelerate(
+            vel,
+            heading,
+            turn,
+            target.length().min(max_speed),
+            delta_seconds,
+        );
+    }
+
+    /// Turns the velocity back towards the previous heading if it turned
+    /// faster than the model allows. The speed is kept.
+    pub fn limit_turn(
+        &self,
+        vel: &mut Velocity,
+        heading: Vec3,
+        delta_seconds: f32,
+    ) {
+        if vel.is_zero() || heading.is_zero() {
+            return;
+        }
+        let max_turn = self.max_turn_rate * delta_seconds;
+        let turn = heading.signed_angle(**vel);
+        if turn.abs() > max_turn {
+            let turned = heading.normalize().rotate(max_turn * turn.signum());
+            *vel = (turned * vel.length()).into();
+        }
+    }
+
+    /// Rotates the heading by given angle in radians and changes the speed
+    /// towards target speed, both as much as the model allows.
+    pub fn turn_and_accelerate(
+        &self,
+        vel: &mut Velocity,
+        heading: Vec3,
+        turn: f32,
+        target_speed: f32,
+        delta_seconds: f32,
+    ) {
+        let max_turn = self.max_turn_rate * delta_seconds;
+        let turn = turn.max(-max_turn).min(max_turn);
+
+        let max_speed_change = self.max_acceleration * delta_seconds;
+        let speed = vel.length();
+        let speed_change = (target_speed - speed)
+            .max(-max_speed_change)
+            .min(max_speed_change);
+
+        *vel = (heading.rotate(turn) * (speed + speed_change).max(0.0)).into();
+    }
+}
@@ -0,0 +1,53 @@
+use crate::prelude::*;
+
+/// Obstacles are square blocks scattered around the map. For now they only
+/// block perception, entities move through them.
+#[derive(Clone, Copy)]
+pub struct Obstacle {
+    pub size: f32,
+}
+
+/// Axis aligned box occupied by an obstacle.
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Spawns obstacles at random places.
+pub fn new(
+    mut commands: Commands,
+    mut rng: ResMut<resources::Rng>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(Color::rgb(0.3, 0.3, 0.3).into());
+    let size = conf::obstacles::SIZE;
+    // Obstacles are off by default, which makes the range empty.
+    #[allow(clippy::reversed_empty_ranges)]
+    for _ in 0..conf::obstacles::COUNT {
+        let translation = Translation::random(&mut rng);
+        commands
+            .spawn(SpriteComponents {
+                material,
+                translation,
+                sprite: Sprite {
+                    size: Vec2::splat(size),
+                },
+                ..Default::default()
+            })
+            .with(Obstacle { size });
+    }
+}
+
+impl Obstacle {
+    /// Returns the box the obstacle occupies if its center is at given
+    /// position.
+    pub fn bounds(&self, pos: Vec3) -> Bounds {
+        let half = Vec2::splat(self.size / 2.0);
+        let center = pos.truncate();
+        Bounds {
+            min: center - half,
+            max: center + half,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Entities see others only inside a cone around their heading. Others which
+//! are very close are heard in any direction, so approaching from behind only
+//! works up to a point.
+
+use crate::{params::Species, prelude::*};
+use std::f32::consts::PI;
+
+/// Describes what an entity perceives around itself.
+#[derive(Clone, Copy, Debug)]
+pub struct Vision {
+    /// How far does the entity see.
+    pub radius: f32,
+    /// Full angle of the view cone in radians. 2π means the entity sees all
+    /// around itself.
+    pub angle: f32,
+    /// Others within this distance are perceived in any direction.
+    pub hearing_radius: f32,
+}
+
+impl Vision {
+    /// Vision of predators.
+    pub fn predator() -> Self {
+        use conf::predator::*;
+        Self {
+            radius: VIEW_RADIUS,
+            angle: VIEW_ANGLE,
+            hearing_radius: HEARING_RADIUS,
+        }
+    }
+
+    /// Vision of prey of given species.
+    pub fn prey(species: &Species) -> Self {
+        Self {
+            radius: species.view_radius,
+            angle: species.view_angle,
+            hearing_radius: species.hearing_radius,
+        }
+    }
+
+    /// Whether an entity at given position and rotation perceives the target.
+    pub fn perceives(&self, pos: Vec3, rot: &Rotation, target: Vec3) -> bool {
+        if self.hears(pos, target) {
+            return true;
+        }
+        let offset = target - pos;
+        let distance = offset.length();
+        if distance >= self.radius {
+            return false;
+        }
+        if self.angle >= 2.0 * PI {
+            return true;
+        }
+
+        let heading = rot.mul_vec3(Vec3::unit_x());
+        heading.signed_angle(offset).abs() <= self.angle / 2.0
+    }
+
+    /// Whether an entity at given position hears the target. Unlike sight,
+    /// hearing isn't blocked by obstacles.
+    pub fn hears(&self, pos: Vec3, target: Vec3) -> bool {
+        pos.distance2(target) < self.hearing_radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sees 100 pixels far in a quarter circle and hears 10 pixels far.
+    fn narrow() -> Vision {
+        Vision {
+            radius: 100.0,
+            angle: PI / 2.0,
+            hearing_radius: 10.0,
+        }
+    }
+
+    #[test]
+    fn sees_inside_of_cone() {
+        let vision = narrow();
+        let rot = Rotation::default();
+
+        assert!(vision.perceives(
+            Vec3::zero(),
+            &rot,
+            Vec3::new(50.0, 0.0, 0.0)
+        ));
+        // Just inside of both edges of the cone.
+        let left = Vec3::unit_x().rotate(PI / 4.0 - 0.01) * 50.0;
+        let right = Vec3::unit_x().rotate(-PI / 4.0 + 0.01) * 50.0;
+        assert!(vision.perceives(Vec3::zero(), &rot, left));
+        assert!(vision.perceives(Vec3::zero(), &rot, right));
+    }
+
+    #[test]
+    fn doesnt_see_outside_of_cone() {
+        let vision = narrow();
+        let rot = Rotation::default();
+
+        // Just outside of both edges of the cone.
+        let left = Vec3::unit_x().rotate(PI / 4.0 + 0.01) * 50.0;
+        let right = Vec3::unit_x().rotate(-PI / 4.0 - 0.01) * 50.0;
+        assert!(!vision.perceives(Vec3::zero(), &rot, left));
+        assert!(!vision.perceives(Vec3::zero(), &rot, right));
+        // Beyond the view radius.
+        let far = Vec3::new(100.0, 0.0, 0.0);
+        assert!(!vision.perceives(Vec3::zero(), &rot, far));
+    }
+
+    #[test]
+    fn cone_turns_with_heading() {
+        let vision = narrow();
+        let rot = Rotation::from_rotation_z(PI / 2.0);
+
+        let ahead = Vec3::new(0.0, 50.0, 0.0);
+        let aside = Vec3::new(50.0, 0.0, 0.0);
+        assert!(vision.perceives(Vec3::zero(), &rot, ahead));
+        assert!(!vision.perceives(Vec3::zero(), &rot, aside));
+    }
+
+    #[test]
+    fn hears_behind_within_hearing_radius() {
+        let vision = narrow();
+        let rot = Rotation::default();
+
+        let close = Vec3::new(-5.0, 0.0, 0.0);
+        let far = Vec3::new(-50.0, 0.0, 0.0);
+        assert!(vision.perceives(Vec3::zero(), &rot, close));
+        assert!(!vision.perceives(Vec3::zero(), &rot, far));
+    }
+
+    #[test]
+    fn full_angle_sees_all_around() {
+        let vision = Vision {
+            angle: 2.0 * PI,
+            ..narrow()
+        };
+        let rot = Rotation::default();
+
+        let behind = Vec3::new(-50.0, 0.0, 0.0);
+        assert!(vision.perceives(Vec3::zero(), &rot, behind));
+        let far = Vec3::new(-100.0, 0.0, 0.0);
+        assert!(!vision.perceives(Vec3::zero(), &rot, far));
+    }
+}
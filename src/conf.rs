@@ -3,9 +3,15 @@
 /// The map is a square where a = MAP_SIZE.
 pub const MAP_SIZE: f32 = 2500.0;
 
+/// Seeds the random number generator and actors' links so that runs with the
+/// same actors joining on the same ticks are reproducible.
+pub const SEED: u64 = 42;
+
 pub mod prey {
-    //! Configuration for prey entity.
+    //! Configuration for prey entity. The constants in this module describe
+    //! the common species, other species override some of them.
 
+    use crate::params::{Escape, Respawn, Species, Weights};
     use std::time::Duration;
 
     /// Location of the prey sprite relative to the root.
@@ -21,6 +27,74 @@ pub mod prey {
     /// this much prey throughout the game.
     pub const COUNT: usize = 50;
 
+    /// Species which populate the map. Each new prey is of a species picked
+    /// at random in proportion to the species' share. Eaten prey respawns as
+    /// the same species and offspring is of the parent's species.
+    pub const SPECIES: &[Species] = &[COMMON, HARE, BISON];
+
+    /// Prey with parameters from this module.
+    pub const COMMON: Species = Species {
+        name: "common",
+        icon: ICON,
+        scale: 1.0,
+        share: 1.0,
+        max_speed: MAX_SPEED,
+        min_speed: MIN_SPEED,
+        max_steering_force: MAX_STEERING_FORCE,
+        view_radius: VIEW_RADIUS,
+        view_angle: VIEW_ANGLE,
+        hearing_radius: HEARING_RADIUS,
+        avoid_radius: AVOID_RADIUS,
+        escape: ESCAPE,
+        weights: weights::COMMON,
+        value: 1.0,
+    };
+
+    /// Small prey which is fast and skittish, but isn't worth much.
+    pub const HARE: Species = Species {
+        name: "hare",
+        scale: 0.7,
+        share: 0.5,
+        max_speed: 600.0,
+        min_speed: 200.0,
+        max_steering_force: 400.0,
+        view_radius: 200.0,
+        hearing_radius: 80.0,
+        escape: Escape::Predictive,
+        weights: Weights {
+            escape: 5.0,
+            ..weights::COMMON
+        },
+        value: 0.5,
+        ..COMMON
+    };
+
+    /// Large prey which is slow and calm, but feeds the whole pack.
+    pub const BISON: Species = Species {
+        name: "bison",
+        scale: 1.5,
+        share: 0.2,
+        max_speed: 350.0,
+        min_speed: 100.0,
+        max_steering_force: 150.0,
+        view_radius: 120.0,
+        avoid_radius: 100.0,
+        weights: Weights {
+            cohesion: 2.0,
+            escape: 2.0,
+            ..weights::COMMON
+        },
+        value: 3.0,
+        ..COMMON
+    };
+
+    /// How does prey flee from predators it sees. Predictive escape makes
+    /// prey harder to catch. Hares always flee predictively.
+    pub const ESCAPE: Escape = Escape::Away;
+
+    /// Where and when does eaten prey respawn.
+    pub const RESPAWN: Respawn = Respawn::Random;
+
     /// How many pixels per tick can a prey move. Make sure that this settings
     /// is always larger than the predators max speed.
     pub const MAX_SPEED: f32 = 500.0;
@@ -34,16 +108,99 @@ pub mod prey {
     /// How much around itself does a prey see.
     pub const VIEW_RADIUS: f32 = 150.0;
 
+    /// Full angle in radians of the cone around its heading in which a prey
+    /// sees. 2π means the prey sees all around itself.
+    pub const VIEW_ANGLE: f32 = 2.0 * std::f32::consts::PI;
+
+    /// Prey hears predators and flockmates this close in any direction.
+    pub const HEARING_RADIUS: f32 = 50.0;
+
     /// Calculating the flocking behavior is expensive. Let's do it only every
     /// now and then and cache the direction vec.
     pub const RECALCULATE_FLOCKING: Duration = Duration::from_millis(50);
 
+    pub mod motion {
+        //! Prey is steered by flocking forces, which are bounded by the max
+        //! steering force.
+
+        use std::f32::consts::PI;
+
+        /// How many radians per second can a prey turn.
+        pub const MAX_TURN_RATE: f32 = 2.0 * PI;
+
+        /// Fraction of velocity a prey loses each second. Prey is always on
+        /// the move, so any drag would only fight the min speed.
+        pub const DRAG: f32 = 0.0;
+    }
+
+    pub mod alarm {
+        //! Prey which spots a predator raises an alarm. The alarm spreads to
+        //! flockmates which see the alarmed prey, one hop per tick, and they
+        //! flee the same way even though they don't see the predator.
+
+        /// Fraction of the alarm level passed on to a flockmate. Zero turns
+        /// alarm propagation off.
+        pub const TRANSMISSION: f32 = 0.0;
+
+        /// How much alarm level does a prey lose each second. Prey which
+        /// spotted a predator has alarm level of 1.
+        pub const DECAY: f32 = 1.0;
+
+        /// Prey with lower alarm level neither flees nor alarms others.
+        pub const THRESHOLD: f32 = 0.1;
+    }
+
+    pub mod stamina {
+        //! Prey gets tired when it flees fast for a long time. This lets
+        //! predators hunt prey down by endurance. Setting the exhausted speed
+        //! to the prey's max speed turns fatigue off.
+
+        /// How many seconds can a prey run at max speed when it's rested.
+        pub const MAX: f32 = 5.0;
+
+        /// Prey doesn't get tired while running at most this fast.
+        pub const CRUISING_SPEED: f32 = 250.0;
+
+        /// How much stamina a prey regains each second while cruising.
+        pub const REGENERATION: f32 = 0.5;
+
+        /// Prey runs at full speed until its stamina drops below this
+        /// fraction of the max.
+        pub const FATIGUE_THRESHOLD: f32 = 0.5;
+
+        /// Max speed of a prey which has no stamina left. Make sure this is
+        /// less than the predators max speed if lone predators should be able
+        /// to catch exhausted prey.
+        pub const EXHAUSTED_SPEED: f32 = 250.0;
+    }
+
+    pub mod actor {
+        //! Prey can be controlled by an actor instead of flocking.
+
+        /// Reward a prey actor receives for each tick it survives.
+        pub const SURVIVAL_REWARD: f32 = 0.01;
+
+        /// Reward a prey actor receives when it's eaten.
+        pub const CAPTURE_REWARD: f32 = -1.0;
+    }
+
     pub mod weights {
+        use super::Weights;
+
         pub const WALL_REPELLING_FORCE: f32 = 2.0;
         pub const ALIGNMENT_FORCE: f32 = 1.0;
         pub const SEPARATION_FORCE: f32 = 1.5;
         pub const COHESION_FORCE: f32 = 1.0;
         pub const ESCAPE_FORCE: f32 = 3.0;
+
+        /// Weights of the common species.
+        pub const COMMON: Weights = Weights {
+            wall_repelling: WALL_REPELLING_FORCE,
+            alignment: ALIGNMENT_FORCE,
+            separation: SEPARATION_FORCE,
+            cohesion: COHESION_FORCE,
+            escape: ESCAPE_FORCE,
+        };
     }
 }
 
@@ -62,10 +219,182 @@ pub mod predator {
     /// predators see other predators.
     pub const VIEW_RADIUS: f32 = 400.0;
 
+    /// Full angle in radians of the cone around its heading in which a
+    /// predator sees. 2π means the predator sees all around itself.
+    pub const VIEW_ANGLE: f32 = 2.0 * std::f32::consts::PI;
+
+    /// Predator hears prey and other predators this close in any direction.
+    /// Make sure it's larger than the strike radius.
+    pub const HEARING_RADIUS: f32 = 50.0;
+
     /// If predator gets at least this close to a prey, it eats it.
     pub const STRIKE_RADIUS: f32 = 30.0;
 
-    /// How many seconds does it take for the predator to go from max speed
-    /// velocity to 0.
-    pub const FRICTION: f32 = 5.0;
+    pub mod motion {
+        //! Predators have inertia. They accelerate and turn at limited rates.
+
+        use std::f32::consts::PI;
+
+        /// How much can a predator change its speed per second.
+        pub const MAX_ACCELERATION: f32 = 600.0;
+
+        /// How many radians per second can a predator turn.
+        pub const MAX_TURN_RATE: f32 = PI;
+
+        /// Fraction of velocity a predator loses each second it doesn't
+        /// accelerate.
+        pub const DRAG: f32 = 0.2;
+    }
+
+    pub mod capture {
+        //! Rules for how predators capture prey and who gets the credit.
+
+        use crate::params::Credit;
+
+        /// How is the reward divided among predators within strike radius.
+        pub const CREDIT: Credit = Credit::Full;
+
+        /// Prey is only captured if at least this many predators are within
+        /// the nearby radius, including those within the strike radius.
+        pub const MIN_PREDATORS: usize = 1;
+
+        /// See the min predators setting.
+        pub const NEARBY_RADIUS: f32 = 100.0;
+
+        /// Predators which saw the prey within this many ticks before it was
+        /// captured, but didn't strike it, get the assist reward.
+        pub const ASSIST_TICKS: u64 = 60;
+
+        /// Reward for an assist. Zero turns assists off.
+        pub const ASSIST_REWARD: f32 = 0.0;
+    }
+
+    pub mod teams {
+        //! Predators can form teams which compete for the same prey.
+
+        /// If true, each predator is rewarded whenever anyone from its team
+        /// captures a prey. Otherwise only the predators which captured it
+        /// are rewarded.
+        pub const SHARED_REWARD: bool = false;
+    }
+
+    pub mod communication {
+        //! Teammates can send each other short messages. A message sent
+        //! together with an action is heard by teammates nearby on the
+        //! following tick and arrives with their observation.
+
+        /// How many floats does a message have.
+        pub const MESSAGE_SIZE: usize = 4;
+
+        /// Only teammates within this distance from the sender hear it.
+        pub const RADIUS: f32 = 300.0;
+
+        /// How many messages can a predator receive per tick. Messages from
+        /// the nearest teammates are kept.
+        pub const BANDWIDTH: usize = 4;
+
+        /// Probability that a message is lost on its way to a teammate.
+        pub const DROPOUT: f32 = 0.0;
+    }
+
+    pub mod hunger {
+        //! Predators lose energy over time and by moving, and regain it by
+        //! eating prey. A predator with no energy left dies.
+
+        /// Whether predators can starve.
+        pub const ENABLED: bool = false;
+
+        /// How much energy does a fed predator have.
+        pub const MAX_ENERGY: f32 = 60.0;
+
+        /// How much energy a predator loses each second.
+        pub const DECAY: f32 = 1.0;
+
+        /// How much energy a predator loses per pixel travelled.
+        pub const MOVEMENT_COST: f32 = 0.002;
+
+        /// How much energy a predator gains by eating a prey.
+        pub const MEAL: f32 = 20.0;
+    }
+
+    pub mod lunge {
+        //! Predators can lunge to briefly run faster than their max speed.
+
+        /// Max speed of a lunging predator.
+        pub const SPEED: f32 = 450.0;
+
+        /// How many seconds does a lunge last.
+        pub const DURATION: f32 = 0.5;
+
+        /// How many seconds must a predator wait after a lunge started before
+        /// it can lunge again.
+        pub const COOLDOWN: f32 = 3.0;
+
+        /// How much stamina does a lunge cost.
+        pub const COST: f32 = 1.0;
+
+        /// How much stamina does a rested predator have.
+        pub const STAMINA: f32 = 3.0;
+
+        /// How much stamina a predator regains each second.
+        pub const REGENERATION: f32 = 0.25;
+    }
+}
+
+pub mod ecology {
+    //! In ecology mode the prey population isn't constant. Eaten prey never
+    //! respawns, instead prey reproduces. Overhunting collapses the
+    //! population.
+
+    /// Whether the ecology mode is on.
+    pub const ENABLED: bool = false;
+
+    /// Prey stops reproducing when there's this many prey in the game.
+    pub const CARRYING_CAPACITY: usize = 200;
+
+    /// Expected number of offspring per prey per second in ideal conditions.
+    pub const BIRTH_RATE: f32 = 0.02;
+
+    /// Prey only reproduces if it has at least one flockmate in its view
+    /// radius. With more flockmates than this the birth rate drops due to
+    /// crowding.
+    pub const CROWDING: usize = 6;
+}
+
+pub mod perception {
+    //! Configuration of how imperfect predators' senses are. The defaults
+    //! make the perception perfect.
+
+    /// Standard deviation of the gaussian noise added to each coordinate of
+    /// the position of a spotted prey.
+    pub const POSITION_NOISE: f32 = 0.0;
+
+    /// Probability that a prey at the edge of predator's view radius is
+    /// detected. The probability decreases linearly from 1 for prey right
+    /// next to the predator to this value.
+    pub const EDGE_DETECTION_PROBABILITY: f32 = 1.0;
+
+    /// Probability that a prey is missed regardless of its distance.
+    pub const FALSE_NEGATIVE_PROBABILITY: f32 = 0.0;
+
+    /// Obstacles always block the line of sight between predators and prey.
+    /// If true, bodies of other predators block it too.
+    pub const BODIES_OCCLUDE: bool = false;
+}
+
+pub mod obstacles {
+    //! Configuration for obstacles.
+
+    /// How many obstacles are spawned when the game starts.
+    pub const COUNT: usize = 0;
+
+    /// Length of the side of each obstacle.
+    pub const SIZE: f32 = 100.0;
+}
+
+pub mod observation {
+    //! Configuration for observations sent to actors.
+
+    /// Rays hit prey and predators as if they were circles with this radius.
+    pub const BODY_RADIUS: f32 = 15.0;
 }
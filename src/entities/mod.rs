@@ -21,18 +21,97 @@ pub mod prey;
 pub use predator::Predator;
 pub use prey::Prey;
 
-use crate::{components::Velocity, prelude::*};
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    components::{obstacles, Motion, Stamina, Team, Velocity, Vision},
+    observation::{raycast::line_of_sight, Sighting},
+    params::{Credit, Respawn},
+    prelude::*,
+};
+
+/// Spawns a new predator or prey for each actor who joined the game since the
+/// last tick.
+pub fn join(
+    mut commands: Commands,
+    lobby: Res<net::Lobby>,
+    mut rng: ResMut<resources::Rng>,
+    mut joins: ResMut<resources::Joins>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    while let Some(join) = lobby.next() {
+        let pos = Translation::random(&mut rng);
+        let seed = joins.next_seed();
+        match join.settings.role {
+            net::Role::Predator => predator::spawn_actor(
+                &mut commands,
+                &mut materials,
+                &asset_server,
+                pos,
+                join,
+                seed,
+            ),
+            net::Role::Prey => prey::spawn_actor(
+                &mut commands,
+                &mut materials,
+                &asset_server,
+                pos,
+                join,
+                seed,
+            ),
+        }
+    }
+}
 
 /// Iterates over all prey in the system and all predators. If a prey is close
 /// to a predator, it checks whether the predator can see it or whether it's
 /// been eaten.
+///
+/// Predators and prey only see each other in their view, see [`Vision`], and
+/// if no obstacle is in the way. Obstacles don't block hearing.
+/// Predators' perception of prey is imperfect, see [`conf::perception`].
+/// Who captures a prey and who gets the credit is decided by rules in
+/// [`conf::predator::capture`].
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn interact(
-    mut prey_query: Query<(&mut Translation, &mut Velocity, &Prey)>,
-    mut predator_query: Query<(&mut Predator, &Translation)>,
+    mut commands: Commands,
+    tick: Res<resources::Tick>,
+    mut rng: ResMut<resources::Rng>,
+    mut respawns: ResMut<resources::PreyRespawns>,
+    mut metrics: ResMut<resources::Metrics>,
+    mut team_scores: ResMut<resources::TeamScores>,
+    mut obstacle_query: Query<(&obstacles::Obstacle, &Translation)>,
+    mut prey_query: Query<(
+        Entity,
+        &mut Translation,
+        &mut Velocity,
+        &Rotation,
+        &mut Stamina,
+        &Vision,
+        &Motion,
+        &mut Prey,
+    )>,
+    mut predator_query: Query<(
+        Entity,
+        &mut Predator,
+        &Translation,
+        &Velocity,
+        &Rotation,
+        &Team,
+        &Vision,
+    )>,
 ) {
+    use conf::predator::capture::*;
+
     struct PredatorData<'a> {
+        entity: Entity,
         rf: Mut<'a, Predator>,
         pos: Vec3,
+        vel: Vec3,
+        rot: Rotation,
+        team: Team,
+        vision: Vision,
     }
 
     // We collect all predator into a vec since we need to refer to it
@@ -40,24 +119,68 @@ pub fn interact(
     // issues.
     let predator_iter = &mut predator_query.iter();
     let mut predators: Vec<_> = Vec::new();
-    for (predator, pos) in predator_iter {
+    for (entity, predator, pos, vel, rot, team, vision) in predator_iter {
         predators.push(PredatorData {
+            entity,
             rf: predator,
             pos: **pos,
+            vel: **vel,
+            rot: *rot,
+            team: *team,
+            vision: *vision,
         });
     }
 
+    // Respawn policies need to know where all entities are.
+    let predator_positions: Vec<_> = predators.iter().map(|p| p.pos).collect();
+
+    // Obstacles and optionally predators' bodies block the line of sight.
+    let mut obstacles = Vec::new();
+    for (obstacle, pos) in &mut obstacle_query.iter() {
+        obstacles.push(obstacle.bounds(**pos));
+    }
+    let bodies = if conf::perception::BODIES_OCCLUDE {
+        predator_positions.clone()
+    } else {
+        Vec::new()
+    };
+    // Prey controlled by actors doesn't flock, so others don't respawn near
+    // it.
+    let mut prey_positions = Vec::new();
+    for (entity, pos, .., this_prey) in &mut prey_query.iter() {
+        if !this_prey.is_controlled() {
+            prey_positions.push((entity, **pos));
+        }
+    }
+
     // This is an inefficient n*k loop, however for our purposes of running the
     // game with well < 10 predators and < 1000 prey it's ok.
-    for (mut prey_pos, mut prey_vel, ..) in &mut prey_query.iter() {
+    // Predators which saw a prey since this tick get an assist if it's
+    // captured.
+    let assist_since = tick.saturating_sub(ASSIST_TICKS);
+
+    for (
+        prey_entity,
+        mut prey_pos,
+        mut prey_vel,
+        prey_rot,
+        mut prey_stamina,
+        prey_vision,
+        prey_motion,
+        mut this_prey,
+    ) in &mut prey_query.iter()
+    {
         // Collects relationships prey has towards predators. We store indexes
         // in the first two arrays. Indexes point to the predator position in
-        // the `predators` array.
+        // the `predators` array. The arrays also store the distance between
+        // the predator and the prey.
         let mut predators_which_eat_me = Vec::new();
         let mut predators_which_see_me = Vec::new();
-        // In this array we store the predator position and the distance between
-        // it and the prey.
-        let mut predators_which_i_see: Vec<(Vec3, f32)> = Vec::new();
+        // How many predators are close enough to help with the capture.
+        let mut predators_nearby = 0;
+        // In this array we store the position and the velocity of predators
+        // the prey sees.
+        let mut predators_which_i_see: Vec<(Vec3, Vec3)> = Vec::new();
 
         // Finds all predators which have one of those relationships with the
         // prey.
@@ -71,80 +194,241 @@ pub fn interact(
                 continue;
             }
 
+            if distance <= NEARBY_RADIUS {
+                predators_nearby += 1;
+            }
+
+            let predator_sees_me = predator.vision.perceives(
+                predator.pos,
+                &predator.rot,
+                **prey_pos,
+            );
+            let i_see_predator =
+                prey_vision.perceives(**prey_pos, prey_rot, predator.pos);
+            // Line of sight is symmetric. Those who hear the other notice it
+            // even behind an obstacle.
+            let visible = (predator_sees_me || i_see_predator)
+                && line_of_sight(predator.pos, **prey_pos, &obstacles, &bodies);
+            let predator_notices_me = predator_sees_me
+                && (visible || predator.vision.hears(predator.pos, **prey_pos));
+            let i_notice_predator = i_see_predator
+                && (visible || prey_vision.hears(**prey_pos, predator.pos));
+
             if distance <= conf::predator::STRIKE_RADIUS {
                 // Prey is within a grasp of a predator - eaten.
-                predators_which_eat_me.push(predator_index);
-            } else {
-                predators_which_see_me.push(predator_index);
+                predators_which_eat_me.push((predator_index, distance));
+            } else if predator_notices_me {
+                predators_which_see_me.push((predator_index, distance));
+            }
 
-                // The prey always has lower or same visibility radius.
-                if distance < conf::prey::VIEW_RADIUS {
-                    predators_which_i_see.push((predator.pos, distance));
-                }
+            if i_notice_predator {
+                predators_which_i_see.push((predator.pos, predator.vel));
             }
         }
 
+        // If there aren't enough predators to capture the prey, those which
+        // strike at it only see it.
+        if predators_nearby < MIN_PREDATORS {
+            predators_which_see_me.append(&mut predators_which_eat_me);
+        }
+
         if !predators_which_eat_me.is_empty() {
-            for predator_index in predators_which_eat_me {
+            let share = match CREDIT {
+                Credit::Full => 1.0,
+                Credit::Split => 1.0 / predators_which_eat_me.len() as f32,
+            };
+            let mut eaters = Vec::with_capacity(predators_which_eat_me.len());
+            for (predator_index, _) in predators_which_eat_me {
                 if let Some(predator) = predators.get_mut(predator_index) {
-                    predator.rf.score();
+                    let reward = predator.rf.score(share, this_prey.species());
+                    eaters.push((predator.entity, predator.team));
+                    team_scores.add(predator.team, reward);
+                }
+            }
+
+            // Teammates share the reward, but not the meal. Each team is
+            // rewarded once no matter how many of its members struck.
+            if conf::predator::teams::SHARED_REWARD {
+                let mut teams: Vec<_> =
+                    eaters.iter().map(|(_, team)| *team).collect();
+                teams.sort_by_key(|team| team.0);
+                teams.dedup();
+                for team in teams {
+                    for teammate in predators.iter_mut().filter(|p| {
+                        p.team == team
+                            && eaters
+                                .iter()
+                                .all(|(eater, _)| *eater != p.entity)
+                    }) {
+                        teammate
+                            .rf
+                            .add_reward(share * this_prey.species().value);
+                    }
                 }
             }
 
-            // Re-spawns the prey at random place somewhere else. This works ok
-            // if the map is very large and there aren't that many predators.
-            // Otherwise prey will spawn straight into the predators.
-            *prey_pos = Translation::random();
+            // Predators which helped to herd the prey get an assist.
+            this_prey.forget_before(assist_since);
+            for witness in this_prey.take_witnesses() {
+                if ASSIST_REWARD == 0.0
+                    || eaters.iter().any(|(eater, _)| *eater == witness)
+                {
+                    continue;
+                }
+                if let Some(predator) =
+                    predators.iter_mut().find(|p| p.entity == witness)
+                {
+                    predator.rf.add_reward(ASSIST_REWARD);
+                }
+            }
+
+            metrics.prey_eaten += 1;
+
+            // Prey controlled by an actor leaves the game once the actor is
+            // told it was eaten.
+            if this_prey.is_controlled() {
+                this_prey.capture();
+                continue;
+            }
+
+            // Re-spawns the prey somewhere else according to the respawn
+            // policy. If the prey doesn't respawn immediately, it leaves the
+            // game. In ecology mode, prey never respawns.
+            let policy = if conf::ecology::ENABLED {
+                Respawn::Never
+            } else {
+                conf::prey::RESPAWN
+            };
+            let flockmates: Vec<_> = prey_positions
+                .iter()
+                .filter(|(entity, _)| *entity != prey_entity)
+                .map(|(_, pos)| *pos)
+                .collect();
+            if let Some(pos) = prey::respawn_position(
+                policy,
+                this_prey.species(),
+                &mut rng,
+                &predator_positions,
+                &flockmates,
+            ) {
+                *prey_pos = pos.into();
+                prey_stamina.refill();
+                this_prey.calm();
+            } else {
+                commands.despawn(prey_entity);
+                if let Respawn::Delayed { seconds } = policy {
+                    respawns.schedule(seconds, *this_prey.species());
+                }
+            }
         } else {
-            if !predators_which_i_see.is_empty() {
-                // Calculates difference between the prey and each predator,
-                // which results in a sum of vectors directed opposite to each
-                // predators position.
+            // Prey controlled by an actor escapes on its own.
+            if !predators_which_i_see.is_empty() && !this_prey.is_controlled() {
+                // Sums unit vectors in which the prey should flee from each
+                // predator according to its species' escape policy.
                 let escape_force = predators_which_i_see.into_iter().fold(
                     Vec3::zero(),
-                    |acc, (predator_pos, distance)| {
-                        acc + (**prey_pos - predator_pos) / distance
+                    |acc, (predator_pos, predator_vel)| {
+                        acc + prey::escape_direction(
+                            this_prey.species().escape,
+                            **prey_pos,
+                            predator_pos,
+                            predator_vel,
+                        )
                     },
-                ) * conf::prey::weights::ESCAPE_FORCE;
+                ) * this_prey.species().weights.escape;
 
-                let acc = prey::steer_towards(*prey_vel, escape_force);
-                prey_vel.apply_acceleration(acc, prey::clamp_speed);
+                let species = this_prey.species();
+                let acc = prey::steer_towards(
+                    species,
+                    prey_motion,
+                    *prey_vel,
+                    escape_force,
+                );
+                prey_vel.apply_acceleration(
+                    acc,
+                    prey::clamp_speed(species, &prey_stamina),
+                );
+                // Startled prey alarms its flockmates.
+                this_prey.startle(escape_force);
             }
 
-            for predator_index in predators_which_see_me {
+            let sighting = Sighting {
+                pos: **prey_pos,
+                vel: **prey_vel,
+                team: None,
+            };
+            for (predator_index, distance) in predators_which_see_me {
                 if let Some(predator) = predators.get_mut(predator_index) {
-                    predator.rf.spot_prey(**prey_pos);
+                    if let Some(sighting) =
+                        perceive(&mut rng, sighting, distance)
+                    {
+                        predator.rf.spot_prey(sighting);
+                        this_prey.witness(predator.entity, **tick);
+                    }
                 }
             }
         }
     }
 }
 
+// Decides whether a predator in given distance detects the prey and if so, how
+// precisely.
+fn perceive(
+    rng: &mut StdRng,
+    sighting: Sighting,
+    distance: f32,
+) -> Option<Sighting> {
+    use conf::perception::*;
+
+    // Perfect perception draws no random numbers, hence the defaults don't
+    // shift the seeded generator for the rest of the simulation.
+    let lossy =
+        EDGE_DETECTION_PROBABILITY < 1.0 || FALSE_NEGATIVE_PROBABILITY > 0.0;
+    if lossy {
+        let detection_probability = 1.0
+            - (1.0 - EDGE_DETECTION_PROBABILITY) * distance
+                / conf::predator::VIEW_RADIUS;
+        if rng.gen::<f32>() >= detection_probability
+            || rng.gen::<f32>() < FALSE_NEGATIVE_PROBABILITY
+        {
+            return None;
+        }
+    }
+
+    if POSITION_NOISE == 0.0 {
+        return Some(sighting);
+    }
+
+    let noise = Vec3::new(gaussian(rng), gaussian(rng), 0.0) * POSITION_NOISE;
+    Some(Sighting {
+        pos: sighting.pos + noise,
+        ..sighting
+    })
+}
+
+// Samples standard normal distribution with Box-Muller transform.
+fn gaussian(rng: &mut StdRng) -> f32 {
+    // Avoids taking logarithm of zero.
+    let u1 = rng.gen::<f32>().max(f32::EPSILON);
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
 // Translates prey or predator based on velocity vector, and also rotates it in
-// the direction of the vector.
+// the direction of the vector. Each species slows down with its own drag.
 //
 // The game space is topological torus in 2 dimensions.
 pub fn nudge(
     time: Res<Time>,
-    mut entity_query: Query<(&mut Velocity, &mut Translation, &mut Rotation)>,
+    mut entity_query: Query<(
+        &mut Velocity,
+        &mut Translation,
+        &mut Rotation,
+        &Motion,
+    )>,
 ) {
-    for (mut vel, mut pos, mut rot) in &mut entity_query.iter() {
-        let mut pos_vec = **pos + **vel * time.delta_seconds;
-
-        if pos_vec.x() > conf::MAP_SIZE {
-            pos_vec.set_x(pos_vec.x() - conf::MAP_SIZE);
-        } else if pos_vec.x() < 0.0 {
-            pos_vec.set_x(pos_vec.x() + conf::MAP_SIZE);
-        }
-
-        if pos_vec.y() > conf::MAP_SIZE {
-            pos_vec.set_y(pos_vec.y() - conf::MAP_SIZE);
-        } else if pos_vec.y() < 0.0 {
-            pos_vec.set_y(pos_vec.y() + conf::MAP_SIZE);
-        }
-
-        pos_vec.set_z(0.0);
-        *pos = pos_vec.into();
+    for (mut vel, mut pos, mut rot, motion) in &mut entity_query.iter() {
+        *pos = integrate(&mut vel, **pos, motion, time.delta_seconds).into();
 
         // If the velocity vector is not zero vector, rotate the entity in the
         // direction of its velocity.
@@ -154,10 +438,104 @@ pub fn nudge(
             // x component, and then shift it if the y component is negative.
             let new_rot = vel_norm.x().acos() * vel_norm.y().signum();
             *rot = Rotation::from_rotation_z(new_rot);
+        }
+    }
+}
+
+// Returns new position of an entity after it moved along its velocity vector
+// for given time, and slows the entity down by its drag.
+fn integrate(
+    vel: &mut Velocity,
+    pos: Vec3,
+    motion: &Motion,
+    delta_seconds: f32,
+) -> Vec3 {
+    let pos_vec = wrap_around(pos + **vel * delta_seconds);
+
+    // Also makes the velocity a little bit smaller. Acts as a "friction".
+    **vel *= 1.0 - delta_seconds * motion.drag;
+
+    pos_vec
+}
+
+// Moves a position which is just outside of the map to the opposite side of
+// it, as the game space is a torus.
+fn wrap_around(mut pos_vec: Vec3) -> Vec3 {
+    if pos_vec.x() > conf::MAP_SIZE {
+        pos_vec.set_x(pos_vec.x() - conf::MAP_SIZE);
+    } else if pos_vec.x() < 0.0 {
+        pos_vec.set_x(pos_vec.x() + conf::MAP_SIZE);
+    }
+
+    if pos_vec.y() > conf::MAP_SIZE {
+        pos_vec.set_y(pos_vec.y() - conf::MAP_SIZE);
+    } else if pos_vec.y() < 0.0 {
+        pos_vec.set_y(pos_vec.y() + conf::MAP_SIZE);
+    }
 
-            // Also makes the velocity a little bit smaller. Acts as a
-            // "friction".
-            **vel *= 1.0 - (time.delta_seconds / conf::predator::FRICTION);
+    pos_vec.set_z(0.0);
+    pos_vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Species;
+
+    #[test]
+    fn prey_integrates_without_drag() {
+        let motion = Motion::prey(&Species::default());
+        let mut vel = Velocity(Vec3::new(200.0, 0.0, 0.0));
+        let pos =
+            integrate(&mut vel, Vec3::new(100.0, 100.0, 0.0), &motion, 0.5);
+
+        assert_eq!(pos, Vec3::new(200.0, 100.0, 0.0));
+        // Prey keeps its speed, otherwise it would fight with its min speed.
+        assert_eq!(*vel, Vec3::new(200.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn predator_integrates_with_its_drag() {
+        let motion = Motion::predator();
+        let mut vel = Velocity(Vec3::new(0.0, 200.0, 0.0));
+        let pos =
+            integrate(&mut vel, Vec3::new(100.0, 100.0, 0.0), &motion, 0.5);
+
+        assert_eq!(pos, Vec3::new(100.0, 200.0, 0.0));
+        let expected_speed = 200.0 * (1.0 - 0.5 * conf::predator::motion::DRAG);
+        assert!((vel.length() - expected_speed).abs() < 1e-3);
+        assert!(vel.length() < 200.0);
+    }
+
+    #[test]
+    fn species_use_different_parameters() {
+        let start = Vec3::new(100.0, 100.0, 0.0);
+        let mut prey_vel = Velocity(Vec3::new(200.0, 0.0, 0.0));
+        let mut predator_vel = prey_vel;
+
+        for _ in 0..10 {
+            integrate(
+                &mut prey_vel,
+                start,
+                &Motion::prey(&Species::default()),
+                0.1,
+            );
+            integrate(&mut predator_vel, start, &Motion::predator(), 0.1);
         }
+
+        assert!(predator_vel.length() < prey_vel.length());
+    }
+
+    #[test]
+    fn wraps_around_the_map() {
+        let mut vel = Velocity(Vec3::new(-200.0, 0.0, 0.0));
+        let pos = integrate(
+            &mut vel,
+            Vec3::new(50.0, 10.0, 0.0),
+            &Motion::prey(&Species::default()),
+            0.5,
+        );
+
+        assert_eq!(pos, Vec3::new(conf::MAP_SIZE - 50.0, 10.0, 0.0));
     }
 }
@@ -6,13 +6,39 @@
 //!
 //! A predator can also be controlled by keyboard for debugging purposes.
 
-use crate::{components::*, prelude::*};
+use rand::Rng;
+
+use crate::{
+    components::*,
+    observation::{Sighting, View},
+    params::Species,
+    prelude::*,
+};
 
 pub struct Predator {
-    // Lists positions of prey nearby. With each tick, this value is reset.
-    nearby_prey: Vec<Vec3>,
-    // Lists positions of nearby predators. With each tick, this value is reset.
-    nearby_predators: Vec<Vec3>,
+    // Lists prey nearby. With each tick, this value is reset.
+    nearby_prey: Vec<Sighting>,
+    // Lists nearby predators. With each tick, this value is reset.
+    nearby_predators: Vec<Sighting>,
+    // Reward received since it was last taken.
+    reward: f32,
+    // When the predator runs out of energy, it dies.
+    energy: f32,
+    // Message the predator broadcasts to teammates on the next tick.
+    outgoing: Option<net::Message>,
+    // Messages heard during this tick together with the distance to their
+    // sender.
+    messages: Vec<(f32, net::Message)>,
+}
+
+/// Predators can lunge to briefly exceed their max speed. Lunging costs
+/// stamina and the predator must wait before it can lunge again.
+#[derive(Default)]
+pub struct Lunge {
+    // Seconds until the lunge ends.
+    active: f32,
+    // Seconds until the predator can lunge again.
+    cooldown: f32,
 }
 
 /// Predators are actors that join over UDP or keyboard actors. When a predator
@@ -20,6 +46,7 @@ pub struct Predator {
 /// TODO: Allow predators join over UDP.
 pub fn init(
     mut commands: Commands,
+    mut rng: ResMut<resources::Rng>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
 ) {
@@ -35,53 +62,155 @@ pub fn init(
     commands.spawn(predator_sprite).with_bundle((
         Predator::new(),
         Velocity::default(),
-        Translation::random(),
+        Translation::random(&mut rng),
         Rotation::default(),
+        Lunge::default(),
+        Stamina::full(conf::predator::lunge::STAMINA),
+        Motion::predator(),
+        Vision::predator(),
+        Team::default(),
         KeyboardControlled,
         camera::Focus,
     ));
 }
 
 /// We find predators which are nearby to each other and update their state.
+/// A predator only sees those in its view, see [`Vision`]. Teammates nearby
+/// hear the messages each other sent on the last tick.
 pub fn find_nearby_predators(
-    mut predator_query: Query<(&mut Predator, &Translation)>,
+    mut rng: ResMut<resources::Rng>,
+    mut predator_query: Query<(
+        &mut Predator,
+        &Translation,
+        &Velocity,
+        &Rotation,
+        &Team,
+        &Vision,
+    )>,
 ) {
+    use conf::predator::communication::*;
+
+    struct PredatorData<'a> {
+        rf: Mut<'a, Predator>,
+        sighting: Sighting,
+        rot: Rotation,
+        vision: Vision,
+        message: Option<net::Message>,
+    }
+
     let mut predators = Vec::new();
     let iter = &mut predator_query.iter();
-    for (predator, pos) in iter {
-        predators.push((predator, **pos));
+    for (mut predator, pos, vel, rot, team, vision) in iter {
+        let message = predator.outgoing.take();
+        predators.push(PredatorData {
+            rf: predator,
+            sighting: Sighting {
+                pos: **pos,
+                vel: **vel,
+                team: Some(*team),
+            },
+            rot: *rot,
+            vision: *vision,
+            message,
+        });
     }
     if predators.is_empty() {
         return;
     }
 
-    // This gets emptied after each cycle so we can reuse it.
+    // Whether a message sent to a teammate at given distance arrives.
+    let mut arrives =
+        |distance: f32| distance < RADIUS && rng.gen::<f32>() >= DROPOUT;
+
+    // These get emptied after each cycle so we can reuse them.
     let mut neighbours = Vec::new();
+    let mut messages = Vec::new();
     // We don't want to visit last predator because by that time we will have
     // visited all other predators and checked whether they are nearby to the
     // last.
     for predator_index in 0..(predators.len() - 1) {
-        let predator_pos = predators.get(predator_index).unwrap().1;
+        let predator = predators.get(predator_index).unwrap();
+        let (predator_sighting, predator_rot, predator_vision) =
+            (predator.sighting, predator.rot, predator.vision);
+        let predator_message = predator.message;
 
         // We've already checked previous predators, so we only check new ones.
         for other_index in (predator_index + 1)..predators.len() {
-            if let Some((other_predator, other_pos)) =
-                predators.get_mut(other_index)
-            {
-                // If the other predator is nearby currently iterated one, push
-                // to the array of neighbours for both predators.
-                let distance = predator_pos.distance2(*other_pos);
-                if distance < conf::predator::VIEW_RADIUS {
-                    neighbours.push(*other_pos);
-                    other_predator.spot_predator(predator_pos);
+            if let Some(other) = predators.get_mut(other_index) {
+                // Predators don't necessarily see each other, one of them
+                // might be behind the other.
+                let pos = predator_sighting.pos;
+                let other_pos = other.sighting.pos;
+                if predator_vision.perceives(pos, &predator_rot, other_pos) {
+                    neighbours.push(other.sighting);
+                }
+                if other.vision.perceives(other_pos, &other.rot, pos) {
+                    other.rf.spot_predator(predator_sighting);
+                }
+
+                // Messages are only exchanged within a team.
+                if predator_sighting.team != other.sighting.team {
+                    continue;
+                }
+                let distance = pos.distance2(other_pos);
+                if let Some(message) = other.message {
+                    if arrives(distance) {
+                        messages.push((distance, message));
+                    }
+                }
+                if let Some(message) = predator_message {
+                    if arrives(distance) {
+                        other.rf.hear(distance, message);
+                    }
                 }
             }
         }
 
-        if let Some((predator, _)) = predators.get_mut(predator_index) {
-            predator.spot_predators(&mut neighbours);
+        if let Some(predator) = predators.get_mut(predator_index) {
+            predator.rf.spot_predators(&mut neighbours);
+            predator.rf.messages.append(&mut messages);
         }
     }
+
+    for mut predator in predators {
+        predator.rf.limit_messages(BANDWIDTH);
+    }
+}
+
+/// Spawns a new predator controlled by the actor who joined the game. The
+/// actor's impaired links are seeded with given seed.
+pub fn spawn_actor(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    pos: Translation,
+    join: net::Join,
+    seed: u64,
+) {
+    let texture_handle = asset_server
+        .load(conf::predator::ICON)
+        .expect("Cannot load predator sprite");
+    let team = join.settings.team;
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(ColorMaterial::modulated_texture(
+                texture_handle,
+                team.color(),
+            )),
+            ..Default::default()
+        })
+        .with_bundle((
+            Predator::new(),
+            Velocity::default(),
+            pos,
+            Rotation::default(),
+            Lunge::default(),
+            Stamina::full(conf::predator::lunge::STAMINA),
+            Motion::predator(),
+            Vision::predator(),
+            team,
+            net::Actor::new(join, seed),
+        ));
 }
 
 #[cfg(feature = "keyboard-control")]
@@ -89,45 +218,196 @@ pub fn find_nearby_predators(
 pub fn keyboard_movement(
     time: Res<Time>,
     keyboard_input: Res<Input<KeyCode>>,
-    mut predator_query: Query<(&mut Velocity, &Predator, &KeyboardControlled)>,
+    mut predator_query: Query<(
+        &mut Velocity,
+        &mut Lunge,
+        &mut Stamina,
+        &Motion,
+        &KeyboardControlled,
+    )>,
 ) {
-    let max_speed = conf::predator::MAX_SPEED;
-    for (mut vel, ..) in &mut predator_query.iter() {
-        let pressed_up = keyboard_input.pressed(KeyCode::Up);
-
-        // Left right keys rotate the entity. Holding right or left key indefinitely
-        // makes the entity go in circles.
-        // If vel was zero, then normalizing would give us gibberish.
-        let left_right = if vel.is_zero() {
-            Vec3::unit_x()
-        } else {
-            let vel_norm = vel.normalize();
-            let vel_perpendicular = vel_norm.perpendicular();
-            if keyboard_input.pressed(KeyCode::Left) {
-                -vel_perpendicular
-            } else if keyboard_input.pressed(KeyCode::Right) {
-                vel_perpendicular
-            } else if pressed_up {
-                // Travels along the current direction, aka continues forward.
-                vel_norm
-            } else {
-                Vec3::zero()
+    let steering = Steering {
+        left: keyboard_input.pressed(KeyCode::Left),
+        right: keyboard_input.pressed(KeyCode::Right),
+        accelerate: keyboard_input.pressed(KeyCode::Up),
+        brake: keyboard_input.pressed(KeyCode::Down),
+    };
+    let lunge_pressed = keyboard_input.pressed(KeyCode::LShift);
+    for (mut vel, mut lunge, mut stamina, motion, ..) in
+        &mut predator_query.iter()
+    {
+        if lunge_pressed {
+            lunge.start(&mut stamina);
+        }
+        let max_speed = lunge.max_speed();
+        steer(&mut vel, steering, motion, max_speed, time.delta_seconds);
+    }
+}
+
+/// Moves those predators which are controlled by actors over the network. An
+/// actor who hung up leaves the game together with their predator.
+pub fn network_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    tick: Res<resources::Tick>,
+    mut predator_query: Query<(
+        Entity,
+        &mut Predator,
+        &mut Velocity,
+        &mut Lunge,
+        &mut Stamina,
+        &Motion,
+        &mut net::Actor,
+    )>,
+) {
+    for (
+        entity,
+        mut predator,
+        mut vel,
+        mut lunge,
+        mut stamina,
+        motion,
+        mut actor,
+    ) in &mut predator_query.iter()
+    {
+        let action = actor.receive_action(**tick);
+        if actor.is_disconnected() {
+            commands.despawn(entity);
+            continue;
+        }
+
+        if actor.take_lunge() {
+            lunge.start(&mut stamina);
+        }
+        if let Some(message) = actor.take_message() {
+            predator.outgoing = Some(message);
+        }
+        let max_speed = lunge.max_speed();
+
+        let dt = time.delta_seconds;
+        match action {
+            Some(net::Action::Continuous(target, _)) => {
+                let target = target.truncate().extend(0.0);
+                motion.approach(&mut vel, target, max_speed, dt);
+            }
+            Some(net::Action::Discrete(action, _)) => {
+                steer(&mut vel, action.into(), motion, max_speed, dt);
             }
-        };
+            // Lunges are never repeated.
+            _ => (),
+        }
+    }
+}
 
-        // Up and down keys respectively speed up and slow down the acceleration.
-        let speed = if pressed_up {
-            max_speed * 2.0
-        } else if keyboard_input.pressed(KeyCode::Down) {
-            max_speed * 0.5
-        } else {
-            max_speed
-        };
+/// Steering is shared by the keyboard and discrete network actions, where
+/// each action corresponds to a key being held.
+#[derive(Default, Clone, Copy)]
+pub struct Steering {
+    pub left: bool,
+    pub right: bool,
+    pub accelerate: bool,
+    pub brake: bool,
+}
+
+impl From<net::DiscreteAction> for Steering {
+    fn from(action: net::DiscreteAction) -> Self {
+        use net::DiscreteAction::*;
+        Self {
+            left: action == TurnLeft,
+            right: action == TurnRight,
+            accelerate: action == Accelerate,
+            brake: action == Brake,
+        }
+    }
+}
+
+/// Updates predator's velocity given which keys are being held. The predator
+/// turns and accelerates as fast as its motion model allows.
+pub fn steer(
+    vel: &mut Velocity,
+    steering: Steering,
+    motion: &Motion,
+    max_speed: f32,
+    delta_seconds: f32,
+) {
+    // If vel was zero, then normalizing would give us gibberish.
+    let heading = if vel.is_zero() {
+        Vec3::unit_x()
+    } else {
+        vel.normalize()
+    };
+
+    // Left right keys rotate the entity. Holding right or left key indefinitely
+    // makes the entity go in circles. The turn is clamped by the max turn
+    // rate.
+    let turn = if steering.left {
+        std::f32::consts::PI
+    } else if steering.right {
+        -std::f32::consts::PI
+    } else {
+        0.0
+    };
+
+    // Up and down keys respectively speed up and slow down the predator. If
+    // neither is held, the predator coasts and drag slows it down. Coasting
+    // never keeps the speed of a finished lunge.
+    let target_speed = if steering.accelerate {
+        max_speed
+    } else if steering.brake {
+        0.0
+    } else {
+        vel.length().min(max_speed)
+    };
+
+    motion.turn_and_accelerate(vel, heading, turn, target_speed, delta_seconds);
+}
+
+/// Ends lunges and cooldowns and regenerates predators' stamina.
+pub fn recover(
+    time: Res<Time>,
+    mut predator_query: Query<(&Predator, &mut Lunge, &mut Stamina)>,
+) {
+    let dt = time.delta_seconds;
+    for (_, mut lunge, mut stamina) in &mut predator_query.iter() {
+        lunge.active = (lunge.active - dt).max(0.0);
+        lunge.cooldown = (lunge.cooldown - dt).max(0.0);
+        stamina.regenerate(conf::predator::lunge::REGENERATION * dt);
+    }
+}
 
-        let acc = left_right * time.delta_seconds * speed;
-        if !acc.is_zero() {
-            // And adds the change in speed to the entity.
-            *vel = ((**vel + acc).normalize() * max_speed).into();
+/// Predators get hungry over time and the more they move, the faster. Those
+/// which run out of energy die. This method MUST be called after
+/// [`network_movement`], which despawns predators of disconnected actors.
+pub fn starve(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut metrics: ResMut<resources::Metrics>,
+    mut predator_query: Query<(
+        Entity,
+        &mut Predator,
+        &Velocity,
+        Option<&net::Actor>,
+    )>,
+) {
+    use conf::predator::hunger::*;
+    if !ENABLED {
+        return;
+    }
+
+    for (entity, mut predator, vel, actor) in &mut predator_query.iter() {
+        // Despawning the same predator twice would panic.
+        if actor.map(net::Actor::is_disconnected) == Some(true) {
+            continue;
+        }
+
+        let distance = vel.length() * time.delta_seconds;
+        predator.energy -=
+            DECAY * time.delta_seconds + MOVEMENT_COST * distance;
+        if predator.is_starved() {
+            // The predator is despawned at the end of this stage, so its
+            // actor still receives the terminal update.
+            commands.despawn(entity);
+            metrics.predators_starved += 1;
         }
     }
 }
@@ -139,6 +419,59 @@ pub fn keyboard_movement(
 pub fn reset_world_view(mut predator_query: Query<&mut Predator>) {
     for mut predator in &mut predator_query.iter() {
         predator.nearby_prey.clear();
+        predator.nearby_predators.clear();
+        predator.messages.clear();
+    }
+}
+
+/// Sends each actor what their predator saw during this tick. This method
+/// MUST be called at the end of each tick after all world updates happened.
+pub fn send_observations(
+    tick: Res<resources::Tick>,
+    mut obstacle_query: Query<(&obstacles::Obstacle, &Translation)>,
+    mut predator_query: Query<(
+        &mut Predator,
+        &Translation,
+        &Velocity,
+        &Rotation,
+        &Stamina,
+        &Team,
+        &mut net::Actor,
+    )>,
+) {
+    let mut obstacles = Vec::new();
+    for (obstacle, pos) in &mut obstacle_query.iter() {
+        obstacles.push(obstacle.bounds(**pos));
+    }
+
+    for (mut predator, pos, vel, rot, stamina, team, mut actor) in
+        &mut predator_query.iter()
+    {
+        // Actors who asked for action repeat only get an update every few
+        // ticks, but the reward is accumulated nevertheless. Actors whose
+        // predator starved get the update right away.
+        let terminal = predator.is_starved();
+        if actor.end_tick(predator.take_reward()) || terminal {
+            let view =
+                View::new(&predator, *team, **pos, **vel, rot, &obstacles);
+            let observation = actor.settings().observation.encode(&view);
+            actor.send_update(
+                net::Frame {
+                    tick: **tick,
+                    vel: **vel,
+                    stamina: stamina.fraction(),
+                    energy: predator.energy
+                        / conf::predator::hunger::MAX_ENERGY,
+                    observation,
+                    messages: predator.take_messages(),
+                },
+                terminal,
+            );
+        }
+
+        // Updates which were delayed by the impaired network might arrive
+        // even on ticks when no update is sent.
+        actor.deliver_updates(**tick);
     }
 }
 
@@ -199,31 +532,111 @@ pub fn change_camera_focus(
     }
 }
 
+impl Lunge {
+    /// Starts a lunge if the predator isn't on cooldown and has enough
+    /// stamina.
+    pub fn start(&mut self, stamina: &mut Stamina) {
+        let cost = conf::predator::lunge::COST;
+        if self.cooldown > 0.0 || stamina.current() < cost {
+            return;
+        }
+
+        stamina.drain(cost);
+        self.active = conf::predator::lunge::DURATION;
+        self.cooldown = conf::predator::lunge::COOLDOWN;
+    }
+
+    /// Predator can run faster while lunging.
+    pub fn max_speed(&self) -> f32 {
+        if self.active > 0.0 {
+            conf::predator::lunge::SPEED
+        } else {
+            conf::predator::MAX_SPEED
+        }
+    }
+}
+
 impl Predator {
-    /// Adds a new prey position into its world view.
-    pub fn spot_prey(&mut self, at: Vec3) {
-        self.nearby_prey.push(at);
+    /// Adds a new prey into its world view.
+    pub fn spot_prey(&mut self, prey: Sighting) {
+        self.nearby_prey.push(prey);
+    }
+
+    /// Adds a new predator into its world view.
+    pub fn spot_predator(&mut self, predator: Sighting) {
+        self.nearby_predators.push(predator);
+    }
+
+    /// Adds new predators into its world view.
+    pub fn spot_predators(&mut self, predators: &mut Vec<Sighting>) {
+        self.nearby_predators.append(predators);
+    }
+
+    /// Prey the predator has seen during this tick.
+    pub fn nearby_prey(&self) -> &[Sighting] {
+        &self.nearby_prey
+    }
+
+    /// Predators the predator has seen during this tick.
+    pub fn nearby_predators(&self) -> &[Sighting] {
+        &self.nearby_predators
+    }
+
+    /// Hears a message from a teammate at given distance.
+    pub fn hear(&mut self, distance: f32, message: net::Message) {
+        self.messages.push((distance, message));
+    }
+
+    /// Keeps only messages from the given number of nearest teammates.
+    pub fn limit_messages(&mut self, bandwidth: usize) {
+        self.messages.sort_by(|(a, _), (b, _)| {
+            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.messages.truncate(bandwidth);
+    }
+
+    /// Takes messages heard during this tick, ordered from the nearest
+    /// sender.
+    pub fn take_messages(&mut self) -> Vec<net::Message> {
+        self.messages
+            .drain(..)
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    /// Rewards the predator for eating given share of a prey and feeds it
+    /// the same share of the prey's meals. Returns the reward.
+    pub fn score(&mut self, share: f32, species: &Species) -> f32 {
+        let reward = share * species.value;
+        self.reward += reward;
+        self.energy = (self.energy + conf::predator::hunger::MEAL * reward)
+            .min(conf::predator::hunger::MAX_ENERGY);
+        reward
     }
 
-    /// Adds a new predator position into its world view.
-    pub fn spot_predator(&mut self, at: Vec3) {
-        self.nearby_predators.push(at);
+    /// Rewards the predator without feeding it, e.g. for an assist.
+    pub fn add_reward(&mut self, reward: f32) {
+        self.reward += reward;
     }
 
-    /// Adds a new predator position into its world view.
-    pub fn spot_predators(&mut self, positions: &mut Vec<Vec3>) {
-        self.nearby_predators.append(positions);
+    /// Whether the predator ran out of energy.
+    pub fn is_starved(&self) -> bool {
+        self.energy <= 0.0
     }
 
-    /// TODO
-    pub fn score(&mut self) {
-        println!("Prey eaten!");
+    /// Returns reward received since the last call and resets it.
+    pub fn take_reward(&mut self) -> f32 {
+        std::mem::replace(&mut self.reward, 0.0)
     }
 
     fn new() -> Self {
         Self {
             nearby_prey: Vec::new(),
             nearby_predators: Vec::new(),
+            reward: 0.0,
+            energy: conf::predator::hunger::MAX_ENERGY,
+            outgoing: None,
+            messages: Vec::new(),
         }
     }
 }
@@ -3,33 +3,294 @@
 //! by predators position and its own position.
 //!
 //! The catch is that the prey is faster than then predator. If the predators
-//! are not organized, they won't get fed.
+//! are not organized, they won't get fed. However prey gets tired if it runs
+//! fast for too long.
 
-use crate::{components::Velocity, prelude::*, resources::FlockUpdateTimer};
+use rand::{rngs::StdRng, Rng};
 
-pub struct Prey;
+use crate::{
+    components::{obstacles, Motion, Stamina, Team, Velocity, Vision},
+    entities::predator,
+    observation::{raycast::line_of_sight, Sighting, View},
+    params::{Escape, Respawn, Species},
+    prelude::*,
+    resources::{FlockUpdateTimer, PreyRespawns},
+};
+
+#[derive(Default)]
+pub struct Prey {
+    species: Species,
+    // Predators which saw the prey recently and the last tick they did.
+    seen_by: Vec<(Entity, u64)>,
+    // Prey controlled by an actor doesn't flock.
+    controlled: bool,
+    // Controlled prey which was eaten waits to tell its actor before it
+    // leaves the game.
+    captured: bool,
+    alarm: Alarm,
+    // Whether the prey spotted a predator itself during this tick.
+    startled: bool,
+    // Whether the prey fled during this tick. Only fleeing prey gets tired.
+    escaping: bool,
+}
+
+// How alarmed is a prey, from 0 to 1, and which way it flees.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Alarm {
+    level: f32,
+    dir: Vec3,
+}
 
 /// Creates initial batch of prey.
 pub fn init(
     mut commands: Commands,
+    mut rng: ResMut<resources::Rng>,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for _ in 0..conf::prey::COUNT {
+        let species = Species::pick(&mut rng);
+        spawn(
+            &mut commands,
+            &mut materials,
+            &asset_server,
+            Translation::random(&mut rng),
+            species,
+        );
+    }
+}
+
+/// Spawns a new rested prey of given species at given position.
+pub fn spawn(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    pos: Translation,
+    species: Species,
+) {
+    let texture_handle = asset_server
+        .load(species.icon)
+        .expect("Cannot load prey sprite");
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(texture_handle.into()),
+            scale: Scale(species.scale),
+            ..Default::default()
+        })
+        .with_bundle((
+            Prey {
+                species,
+                ..Default::default()
+            },
+            Velocity::default(),
+            Stamina::full(conf::prey::stamina::MAX),
+            Motion::prey(&species),
+            Vision::prey(&species),
+            pos,
+            Rotation::default(),
+        ));
+}
+
+/// Spawns a new prey controlled by the actor who joined the game. Actors
+/// always play the common species. The actor's impaired links are seeded with
+/// given seed.
+pub fn spawn_actor(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    asset_server: &AssetServer,
+    pos: Translation,
+    join: net::Join,
+    seed: u64,
 ) {
     let texture_handle = asset_server
         .load(conf::prey::ICON)
         .expect("Cannot load prey sprite");
-    for _ in 0..conf::prey::COUNT {
-        commands
-            .spawn(SpriteComponents {
-                material: materials.add(texture_handle.into()),
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(texture_handle.into()),
+            ..Default::default()
+        })
+        .with_bundle((
+            Prey {
+                controlled: true,
                 ..Default::default()
+            },
+            Velocity::default(),
+            Stamina::full(conf::prey::stamina::MAX),
+            Motion::prey(&Species::default()),
+            Vision::prey(&Species::default()),
+            pos,
+            Rotation::default(),
+            net::Actor::new(join, seed),
+        ));
+}
+
+/// Returns where should an eaten prey of given species respawn given the
+/// respawn policy and positions of all predators and other prey. If the prey
+/// shouldn't respawn immediately, returns None.
+pub fn respawn_position(
+    policy: Respawn,
+    species: &Species,
+    rng: &mut StdRng,
+    predators: &[Vec3],
+    prey: &[Vec3],
+) -> Option<Vec3> {
+    // How many random positions are tried before we give up looking for one
+    // which is far enough from predators.
+    const ATTEMPTS: usize = 100;
+
+    match policy {
+        Respawn::Never | Respawn::Delayed { .. } => None,
+        Respawn::Random => Some(*Translation::random(rng)),
+        Respawn::AwayFromPredators { min_distance } => {
+            // How far is the closest predator.
+            let clearance = |pos: Vec3| {
+                predators
+                    .iter()
+                    .map(|p| p.distance2(pos))
+                    .fold(f32::INFINITY, f32::min)
+            };
+            // If no position is far enough from all predators, falls back to
+            // the tried position which is the farthest from them.
+            let mut best = *Translation::random(rng);
+            for _ in 1..ATTEMPTS {
+                if clearance(best) >= min_distance {
+                    break;
+                }
+                let pos = *Translation::random(rng);
+                if clearance(pos) > clearance(best) {
+                    best = pos;
+                }
+            }
+            Some(best)
+        }
+        Respawn::MapEdge => {
+            // Prey spawns within this distance from the edge.
+            let margin = conf::MAP_SIZE / 20.0;
+            let along = rng.gen_range(0.0, conf::MAP_SIZE);
+            let across = rng.gen_range(0.0, margin);
+            let pos = match rng.gen_range(0, 4) {
+                0 => Vec3::new(along, across, 0.0),
+                1 => Vec3::new(along, conf::MAP_SIZE - across, 0.0),
+                2 => Vec3::new(across, along, 0.0),
+                _ => Vec3::new(conf::MAP_SIZE - across, along, 0.0),
+            };
+            Some(pos)
+        }
+        Respawn::NearFlock => {
+            if prey.is_empty() {
+                return Some(*Translation::random(rng));
+            }
+            let flockmate = prey[rng.gen_range(0, prey.len())];
+            // Spawns the prey within flockmate's view but not too close to it.
+            let distance =
+                rng.gen_range(species.avoid_radius, species.view_radius);
+            let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+            let pos = flockmate + Vec3::unit_x().rotate(angle) * distance;
+            Some(Vec3::new(
+                pos.x().clamp(0.0, conf::MAP_SIZE),
+                pos.y().clamp(0.0, conf::MAP_SIZE),
+                0.0,
+            ))
+        }
+    }
+}
+
+/// In ecology mode, prey reproduces at a rate which depends on the density of
+/// the flock around it and on how close the population is to the carrying
+/// capacity. Offspring of the parent's species is spawned next to it.
+pub fn reproduce(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rng: ResMut<resources::Rng>,
+    mut metrics: ResMut<resources::Metrics>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut prey_query: Query<(&Prey, &Translation)>,
+) {
+    use conf::ecology::*;
+    if !ENABLED {
+        return;
+    }
+
+    let mut prey = Vec::new();
+    for (this_prey, pos) in &mut prey_query.iter() {
+        prey.push((**pos, this_prey.species));
+    }
+
+    // Logistic growth, the closer to the capacity the slower it is.
+    let capacity_factor = 1.0 - prey.len() as f32 / CARRYING_CAPACITY as f32;
+    if capacity_factor <= 0.0 {
+        return;
+    }
+
+    let mut offspring = Vec::new();
+    for (index, (parent, species)) in prey.iter().enumerate() {
+        let flockmates = prey
+            .iter()
+            .enumerate()
+            .filter(|(other_index, (other, _))| {
+                *other_index != index
+                    && parent.distance2(*other) < species.view_radius
             })
-            .with_bundle((
-                Prey,
-                Velocity::default(),
-                Translation::random(),
-                Rotation::default(),
-            ));
+            .count();
+        // Lone prey cannot reproduce, crowded prey reproduces slower.
+        let density_factor = if flockmates == 0 {
+            0.0
+        } else {
+            (CROWDING as f32 / flockmates as f32).min(1.0)
+        };
+
+        let probability =
+            BIRTH_RATE * capacity_factor * density_factor * time.delta_seconds;
+        if rng.gen::<f32>() < probability {
+            let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+            // Offspring of prey next to the edge is born on the other side
+            // of the map, just like prey which runs across the edge.
+            let pos = super::wrap_around(
+                *parent + Vec3::unit_x().rotate(angle) * species.avoid_radius,
+            );
+            offspring.push((pos, *species));
+        }
+    }
+
+    for (pos, species) in offspring {
+        metrics.prey_born += 1;
+        spawn(
+            &mut commands,
+            &mut materials,
+            &asset_server,
+            pos.into(),
+            species,
+        );
+    }
+}
+
+/// Counts prey in the game on each tick.
+pub fn count_population(
+    mut metrics: ResMut<resources::Metrics>,
+    mut prey_query: Query<&Prey>,
+) {
+    metrics.prey_population = prey_query.iter().iter().count();
+}
+
+/// Spawns prey whose delayed respawn is due.
+pub fn respawn_delayed(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut respawns: ResMut<PreyRespawns>,
+    mut rng: ResMut<resources::Rng>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for species in respawns.tick(time.delta_seconds) {
+        spawn(
+            &mut commands,
+            &mut materials,
+            &asset_server,
+            Translation::random(&mut rng),
+            species,
+        );
     }
 }
 
@@ -43,7 +304,15 @@ pub fn init(
 pub fn flocking_behavior(
     time: Res<Time>,
     mut timer: ResMut<FlockUpdateTimer>,
-    mut prey_query: Query<(&Prey, &mut Velocity, &Translation)>,
+    mut prey_query: Query<(
+        &Prey,
+        &mut Velocity,
+        &Translation,
+        &Rotation,
+        &Stamina,
+        &Vision,
+        &Motion,
+    )>,
 ) {
     // Ticks and checks that enough time has passed and its time to update the
     // flocking again.
@@ -56,6 +325,12 @@ pub fn flocking_behavior(
     struct PreyData<'a> {
         vel: Mut<'a, Velocity>,
         pos: Vec3,
+        rot: Rotation,
+        stamina: Stamina,
+        vision: Vision,
+        motion: Motion,
+        species: Species,
+        controlled: bool,
     }
 
     // We collect all prey into a vec since we need to run a loop which
@@ -63,15 +338,31 @@ pub fn flocking_behavior(
     // game. This is not currently possible with the iterator.
     let prey_iter = &mut prey_query.iter();
     let mut prey = Vec::with_capacity(conf::prey::COUNT);
-    for (_, velocity, translation) in prey_iter {
+    for (this_prey, velocity, translation, rot, stamina, vision, motion) in
+        prey_iter
+    {
         prey.push(PreyData {
             vel: velocity,
             pos: **translation,
+            rot: *rot,
+            stamina: *stamina,
+            vision: *vision,
+            motion: *motion,
+            species: this_prey.species,
+            controlled: this_prey.controlled,
         });
     }
 
     for prey_index in 0..prey.len() {
         let iterated_prey = &prey[prey_index];
+        // Controlled prey is a flockmate to other prey, but it isn't steered
+        // by the flock.
+        if iterated_prey.controlled {
+            continue;
+        }
+        let species = iterated_prey.species;
+        let motion = iterated_prey.motion;
+        let weights = species.weights;
 
         // How many other prey is nearby.
         let mut flockmates = 0;
@@ -91,7 +382,11 @@ pub fn flocking_behavior(
             let offset = iterated_prey.pos - other_prey.pos;
             let sq_distance = offset.length_squared();
 
-            if sq_distance < conf::prey::VIEW_RADIUS.powi(2) {
+            if iterated_prey.vision.perceives(
+                iterated_prey.pos,
+                &iterated_prey.rot,
+                other_prey.pos,
+            ) {
                 flockmates += 1;
                 // Used to calculate affect of alignment force. See below.
                 heading_dir += **other_prey.vel;
@@ -100,7 +395,7 @@ pub fn flocking_behavior(
 
                 // If prey is too close to each other, try change its direction
                 // so that they don't bump.
-                if sq_distance < conf::prey::AVOID_RADIUS.powi(2) {
+                if sq_distance < species.avoid_radius.powi(2) {
                     separation_dir += offset / (sq_distance + f32::EPSILON);
                 }
             }
@@ -110,9 +405,9 @@ pub fn flocking_behavior(
         let mut acc = Vec3::zero();
 
         // If the prey gets too close to a wall, we push it out.
-        if let Some(f) = wall_repelling_force(iterated_prey.pos) {
-            acc += steer_towards(*iterated_prey.vel, f)
-                * conf::prey::weights::WALL_REPELLING_FORCE;
+        if let Some(f) = wall_repelling_force(&species, iterated_prey.pos) {
+            acc += steer_towards(&species, &motion, *iterated_prey.vel, f)
+                * weights.wall_repelling;
         }
 
         if flockmates > 0 {
@@ -121,61 +416,575 @@ pub fn flocking_behavior(
                 // to current position is taken.
                 let offset_to_flock_center =
                     (center_total / flockmates as f32) - iterated_prey.pos;
-                steer_towards(*iterated_prey.vel, offset_to_flock_center)
+                steer_towards(
+                    &species,
+                    &motion,
+                    *iterated_prey.vel,
+                    offset_to_flock_center,
+                )
             };
-            acc += cohesion_force * conf::prey::weights::COHESION_FORCE;
+            acc += cohesion_force * weights.cohesion;
 
             // Aligns velocity vectors with nearby flockmates.
-            let alignment_force =
-                steer_towards(*iterated_prey.vel, heading_dir);
-            acc += alignment_force * conf::prey::weights::ALIGNMENT_FORCE;
+            let alignment_force = steer_towards(
+                &species,
+                &motion,
+                *iterated_prey.vel,
+                heading_dir,
+            );
+            acc += alignment_force * weights.alignment;
 
             // If there is some separation to be sustained with nearby
             // flockmates, apply the force to the acceleration.
             if !separation_dir.is_zero() {
-                let separation_force =
-                    steer_towards(*iterated_prey.vel, separation_dir);
-                acc += separation_force * conf::prey::weights::SEPARATION_FORCE;
+                let separation_force = steer_towards(
+                    &species,
+                    &motion,
+                    *iterated_prey.vel,
+                    separation_dir,
+                );
+                acc += separation_force * weights.separation;
             }
         }
 
-        // If the entity picked up some acceleration.
+        // If the entity picked up some acceleration. The prey turns only as
+        // fast as its motion model allows.
         if !acc.is_zero() {
+            let heading = **iterated_prey.vel;
             iterated_prey.vel.apply_acceleration_over_time(
                 acc,
                 conf::prey::RECALCULATE_FLOCKING,
-                clamp_speed,
+                clamp_speed(&species, &iterated_prey.stamina),
+            );
+            motion.limit_turn(
+                &mut iterated_prey.vel,
+                heading,
+                conf::prey::RECALCULATE_FLOCKING.as_secs_f32(),
             );
         }
     }
 }
 
-/// Given prey's current velocity, we apply force to it.
-pub fn steer_towards(velocity: Velocity, force: Vec3) -> Vec3 {
-    let v = force.normalize() * conf::prey::MAX_SPEED - *velocity;
-    v.min(Vec3::splat(conf::prey::MAX_STEERING_FORCE))
+/// Returns unit vector in which a prey should flee from a predator given the
+/// escape policy.
+pub fn escape_direction(
+    policy: Escape,
+    prey_pos: Vec3,
+    predator_pos: Vec3,
+    predator_vel: Vec3,
+) -> Vec3 {
+    let offset = prey_pos - predator_pos;
+    if offset.is_zero() {
+        return Vec3::zero();
+    }
+    let away = offset.normalize();
+
+    let sq_speed = predator_vel.length_squared();
+    if policy == Escape::Away || sq_speed < f32::EPSILON {
+        return away;
+    }
+
+    // If the predator keeps its course, this is when it gets closest to the
+    // prey. A predator which moves away from the prey never intercepts it.
+    let time_to_intercept = offset.dot(predator_vel) / sq_speed;
+    if time_to_intercept <= 0.0 {
+        return away;
+    }
+
+    let intercept = predator_pos + predator_vel * time_to_intercept;
+    let from_intercept = prey_pos - intercept;
+    if from_intercept.length() < conf::observation::BODY_RADIUS {
+        // The predator heads right at the prey, so it dodges to the left of
+        // the predator's course.
+        Vec3::new(-predator_vel.y(), predator_vel.x(), 0.0).normalize()
+    } else {
+        from_intercept.normalize()
+    }
+}
+
+/// Given prey's current velocity, we apply force to it. The force is bounded
+/// by the prey's max acceleration.
+pub fn steer_towards(
+    species: &Species,
+    motion: &Motion,
+    velocity: Velocity,
+    force: Vec3,
+) -> Vec3 {
+    let v = force.normalize() * species.max_speed - *velocity;
+    v.min(Vec3::splat(motion.max_acceleration))
 }
 
-/// Clamps prey speed.
-pub fn clamp_speed(speed: f32) -> f32 {
+/// Clamps prey speed. Tired prey cannot run as fast.
+pub fn clamp_speed(
+    species: &Species,
+    stamina: &Stamina,
+) -> impl Fn(f32) -> f32 {
+    let min_speed = species.min_speed;
+    let max_speed = max_speed(species, stamina);
     // Unfortunately clamp is still in nightly.
-    speed.max(conf::prey::MIN_SPEED).min(conf::prey::MAX_SPEED)
+    move |speed| speed.max(min_speed).min(max_speed)
+}
+
+/// Returns how fast can a prey run given how tired it is. Prey runs at full
+/// speed until its stamina drops below the fatigue threshold, then its max
+/// speed decreases linearly down to the exhausted speed. Species slower than
+/// the exhausted speed don't slow down.
+pub fn max_speed(species: &Species, stamina: &Stamina) -> f32 {
+    use conf::prey::stamina::*;
+    let exhausted_speed = EXHAUSTED_SPEED.min(species.max_speed);
+    let rested = (stamina.fraction() / FATIGUE_THRESHOLD).min(1.0);
+    exhausted_speed + (species.max_speed - exhausted_speed) * rested
+}
+
+/// Moves those prey which are controlled by actors over the network. Prey
+/// cannot lunge and doesn't talk, so those actions are ignored. An actor who
+/// hung up leaves the game together with their prey.
+pub fn network_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    tick: Res<resources::Tick>,
+    mut prey_query: Query<(
+        Entity,
+        &Prey,
+        &mut Velocity,
+        &Stamina,
+        &Motion,
+        &mut net::Actor,
+    )>,
+) {
+    for (entity, this_prey, mut vel, stamina, motion, mut actor) in
+        &mut prey_query.iter()
+    {
+        let action = actor.receive_action(**tick);
+        if actor.is_disconnected() {
+            commands.despawn(entity);
+            continue;
+        }
+        actor.take_lunge();
+        actor.take_message();
+
+        let species = this_prey.species;
+        let max_speed = max_speed(&species, stamina);
+        let dt = time.delta_seconds;
+        match action {
+            Some(net::Action::Continuous(target, _)) => {
+                let target = target.truncate().extend(0.0);
+                motion.approach(&mut vel, target, max_speed, dt);
+            }
+            Some(net::Action::Discrete(action, _)) => {
+                predator::steer(&mut vel, action.into(), motion, max_speed, dt);
+            }
+            _ => (),
+        }
+
+        // Even controlled prey is always on the move and gets tired.
+        let speed = vel.length();
+        if speed > 0.0 {
+            **vel = **vel / speed * clamp_speed(&species, stamina)(speed);
+        }
+    }
+}
+
+/// Sends each prey actor what their prey saw during this tick and the reward
+/// for surviving it. Actors whose prey was eaten get a terminal update and
+/// the prey leaves the game. This method MUST be called at the end of each
+/// tick after all world updates happened.
+#[allow(clippy::type_complexity)]
+pub fn send_observations(
+    mut commands: Commands,
+    tick: Res<resources::Tick>,
+    mut obstacle_query: Query<(&obstacles::Obstacle, &Translation)>,
+    mut predator_query: Query<(
+        &predator::Predator,
+        &Translation,
+        &Velocity,
+        &Team,
+    )>,
+    mut prey_query: Query<(Entity, &Prey, &Translation, &Velocity)>,
+    mut actor_query: Query<(
+        Entity,
+        &Prey,
+        &Translation,
+        &Velocity,
+        &Rotation,
+        &Stamina,
+        &Vision,
+        &mut net::Actor,
+    )>,
+) {
+    use conf::prey::actor::*;
+
+    let mut obstacles = Vec::new();
+    for (obstacle, pos) in &mut obstacle_query.iter() {
+        obstacles.push(obstacle.bounds(**pos));
+    }
+    let mut predators = Vec::new();
+    for (_, pos, vel, team) in &mut predator_query.iter() {
+        predators.push(Sighting {
+            pos: **pos,
+            vel: **vel,
+            team: Some(*team),
+        });
+    }
+    // Obstacles and optionally predators' bodies block the line of sight.
+    let bodies: Vec<_> = if conf::perception::BODIES_OCCLUDE {
+        predators.iter().map(|p| p.pos).collect()
+    } else {
+        Vec::new()
+    };
+    let mut prey = Vec::new();
+    for (entity, _, pos, vel) in &mut prey_query.iter() {
+        let sighting = Sighting {
+            pos: **pos,
+            vel: **vel,
+            team: None,
+        };
+        prey.push((entity, sighting));
+    }
+
+    for (entity, this_prey, pos, vel, rot, stamina, vision, mut actor) in
+        &mut actor_query.iter()
+    {
+        // The prey of a disconnected actor is already despawned.
+        if actor.is_disconnected() {
+            continue;
+        }
+
+        // Prey perceives everything in its view which isn't hidden, and hears
+        // everything close enough even behind obstacles.
+        let nearby = |sightings: &[Sighting]| -> Vec<Sighting> {
+            sightings
+                .iter()
+                .filter(|s| {
+                    vision.hears(**pos, s.pos)
+                        || vision.perceives(**pos, rot, s.pos)
+                            && line_of_sight(**pos, s.pos, &obstacles, &bodies)
+                })
+                .copied()
+                .collect()
+        };
+
+        let terminal = this_prey.captured;
+        let reward = if terminal {
+            CAPTURE_REWARD
+        } else {
+            SURVIVAL_REWARD
+        };
+        if actor.end_tick(reward) || terminal {
+            // The prey doesn't see itself.
+            let others: Vec<_> = prey
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, sighting)| *sighting)
+                .collect();
+            let nearby_prey = nearby(&others);
+            let nearby_predators = nearby(&predators);
+            let view = View::of_prey(
+                &nearby_prey,
+                &nearby_predators,
+                vision.radius,
+                **pos,
+                **vel,
+                rot,
+                &obstacles,
+            );
+            let observation = actor.settings().observation.encode(&view);
+            actor.send_update(
+                net::Frame {
+                    tick: **tick,
+                    vel: **vel,
+                    stamina: stamina.fraction(),
+                    energy: 1.0,
+                    observation,
+                    messages: Vec::new(),
+                },
+                terminal,
+            );
+        }
+
+        if terminal {
+            commands.despawn(entity);
+        } else {
+            // Updates which were delayed by the impaired network might
+            // arrive even on ticks when no update is sent.
+            actor.deliver_updates(**tick);
+        }
+    }
+}
+
+/// Spreads alarm from prey which spotted a predator to flockmates which see
+/// them. The alarm weakens with each hop and fades over time. Alarmed prey
+/// flees in the same direction as the prey which alarmed it. See
+/// [`conf::prey::alarm`].
+#[allow(clippy::type_complexity)]
+pub fn spread_alarm(
+    time: Res<Time>,
+    mut obstacle_query: Query<(&obstacles::Obstacle, &Translation)>,
+    mut predator_query: Query<(&predator::Predator, &Translation)>,
+    mut prey_query: Query<(
+        &mut Prey,
+        &mut Velocity,
+        &Translation,
+        &Rotation,
+        &Stamina,
+        &Vision,
+        &Motion,
+    )>,
+) {
+    use conf::prey::alarm::*;
+    if TRANSMISSION == 0.0 {
+        return;
+    }
+
+    struct PreyData<'a> {
+        rf: Mut<'a, Prey>,
+        vel: Mut<'a, Velocity>,
+        pos: Vec3,
+        rot: Rotation,
+        stamina: Stamina,
+        vision: Vision,
+        motion: Motion,
+    }
+
+    // Obstacles and optionally predators' bodies block the line of sight.
+    let mut obstacles = Vec::new();
+    for (obstacle, pos) in &mut obstacle_query.iter() {
+        obstacles.push(obstacle.bounds(**pos));
+    }
+    let mut bodies = Vec::new();
+    if conf::perception::BODIES_OCCLUDE {
+        for (_, pos) in &mut predator_query.iter() {
+            bodies.push(**pos);
+        }
+    }
+
+    let prey_iter = &mut prey_query.iter();
+    let mut prey = Vec::new();
+    for (this_prey, vel, pos, rot, stamina, vision, motion) in prey_iter {
+        prey.push(PreyData {
+            rf: this_prey,
+            vel,
+            pos: **pos,
+            rot: *rot,
+            stamina: *stamina,
+            vision: *vision,
+            motion: *motion,
+        });
+    }
+
+    let alarms: Vec<_> = prey.iter().map(|p| p.rf.alarm).collect();
+    let alarms = spread(
+        &alarms,
+        |prey_index, other_index| {
+            let (this_prey, other) = (&prey[prey_index], &prey[other_index]);
+            this_prey
+                .vision
+                .perceives(this_prey.pos, &this_prey.rot, other.pos)
+                && line_of_sight(this_prey.pos, other.pos, &obstacles, &bodies)
+        },
+        TRANSMISSION,
+        time.delta_seconds,
+    );
+
+    for (iterated_prey, alarm) in prey.iter_mut().zip(alarms) {
+        let this_prey = &mut iterated_prey.rf;
+        this_prey.alarm = alarm;
+        // Prey which spotted a predator already fled from it.
+        let startled = std::mem::replace(&mut this_prey.startled, false);
+        if startled
+            || this_prey.controlled
+            || alarm.level < THRESHOLD
+            || alarm.dir.is_zero()
+        {
+            continue;
+        }
+
+        let species = this_prey.species;
+        let acc = steer_towards(
+            &species,
+            &iterated_prey.motion,
+            *iterated_prey.vel,
+            alarm.dir,
+        ) * species.weights.escape
+            * alarm.level;
+        iterated_prey.vel.apply_acceleration(
+            acc,
+            clamp_speed(&species, &iterated_prey.stamina),
+        );
+        this_prey.escaping = true;
+    }
+}
+
+// Returns alarms of the whole flock after a tick. Each prey is alarmed by
+// those flockmates it sees, as told by `sees(prey, flockmate)`, with their
+// alarms from the beginning of the tick. Hence the alarm travels one hop per
+// tick.
+fn spread(
+    alarms: &[Alarm],
+    sees: impl Fn(usize, usize) -> bool,
+    transmission: f32,
+    delta_seconds: f32,
+) -> Vec<Alarm> {
+    use conf::prey::alarm::*;
+    alarms
+        .iter()
+        .enumerate()
+        .map(|(prey_index, alarm)| {
+            // Calm flockmates are skipped before the costly check of whether
+            // the prey sees them.
+            let heard =
+                alarms.iter().enumerate().filter(|(other_index, other)| {
+                    *other_index != prey_index
+                        && other.level >= THRESHOLD
+                        && sees(prey_index, *other_index)
+                });
+            alarm.relay(
+                heard.map(|(_, other)| *other),
+                transmission,
+                delta_seconds,
+            )
+        })
+        .collect()
+}
+
+/// Prey which flees faster than its cruising speed gets tired, otherwise it
+/// regains its stamina. Flocking prey runs at its max speed too, but it only
+/// gets tired while it flees. Controlled prey gets tired whenever it runs
+/// fast.
+pub fn fatigue(
+    time: Res<Time>,
+    mut prey_query: Query<(&mut Prey, &Velocity, &mut Stamina)>,
+) {
+    for (mut this_prey, vel, mut stamina) in &mut prey_query.iter() {
+        let escaping = std::mem::replace(&mut this_prey.escaping, false);
+        tire(
+            &mut stamina,
+            &this_prey.species,
+            vel.length(),
+            escaping || this_prey.controlled,
+            time.delta_seconds,
+        );
+    }
+}
+
+// Drains the stamina of a prey which exerts itself by running faster than its
+// cruising speed for given time, otherwise regenerates it.
+fn tire(
+    stamina: &mut Stamina,
+    species: &Species,
+    speed: f32,
+    exerted: bool,
+    delta_seconds: f32,
+) {
+    use conf::prey::stamina::*;
+    if exerted && speed > CRUISING_SPEED {
+        // The faster the prey runs, the faster it gets tired. Running at max
+        // speed drains one unit of stamina per second.
+        let exertion =
+            (speed - CRUISING_SPEED) / (species.max_speed - CRUISING_SPEED);
+        stamina.drain(exertion * delta_seconds);
+    } else {
+        stamina.regenerate(REGENERATION * delta_seconds);
+    }
+}
+
+impl Prey {
+    pub fn species(&self) -> &Species {
+        &self.species
+    }
+
+    /// Remembers that the predator saw the prey at given tick.
+    pub fn witness(&mut self, predator: Entity, tick: u64) {
+        if let Some(seen) =
+            self.seen_by.iter_mut().find(|(p, _)| *p == predator)
+        {
+            seen.1 = tick;
+        } else {
+            self.seen_by.push((predator, tick));
+        }
+    }
+
+    /// Forgets predators which haven't seen the prey since given tick.
+    pub fn forget_before(&mut self, tick: u64) {
+        self.seen_by.retain(|(_, seen_at)| *seen_at >= tick);
+    }
+
+    /// Whether the prey is controlled by an actor.
+    pub fn is_controlled(&self) -> bool {
+        self.controlled
+    }
+
+    /// The prey spotted a predator and flees in given direction. It's fully
+    /// alarmed, unless the alarm doesn't spread.
+    pub fn startle(&mut self, dir: Vec3) {
+        self.escaping = true;
+        if conf::prey::alarm::TRANSMISSION > 0.0 {
+            self.alarm = Alarm { level: 1.0, dir };
+            self.startled = true;
+        }
+    }
+
+    /// Forgets any alarm, e.g. when the prey respawns.
+    pub fn calm(&mut self) {
+        self.alarm = Alarm::default();
+    }
+
+    /// Marks controlled prey as eaten.
+    pub fn capture(&mut self) {
+        self.captured = true;
+    }
+
+    /// Returns predators which saw the prey and forgets them.
+    pub fn take_witnesses(&mut self) -> Vec<Entity> {
+        self.seen_by
+            .drain(..)
+            .map(|(predator, _)| predator)
+            .collect()
+    }
+}
+
+impl Alarm {
+    // Returns the alarm after it faded for given time and the prey heard the
+    // alarm of given flockmates. The strongest relayed alarm wins if it's
+    // stronger than the prey's own.
+    fn relay(
+        self,
+        heard: impl Iterator<Item = Alarm>,
+        transmission: f32,
+        delta_seconds: f32,
+    ) -> Self {
+        let level = self.level - conf::prey::alarm::DECAY * delta_seconds;
+        let mut alarm = Self {
+            level: level.max(0.0),
+            ..self
+        };
+        for other in heard {
+            let relayed = other.level * transmission;
+            if relayed > alarm.level {
+                alarm = Self {
+                    level: relayed,
+                    dir: other.dir,
+                };
+            }
+        }
+        alarm
+    }
 }
 
 // If the prey is too close to the wall, it attempts to run away from it.
-fn wall_repelling_force(pos: Vec3) -> Option<Vec3> {
+fn wall_repelling_force(species: &Species, pos: Vec3) -> Option<Vec3> {
     let map_10p = conf::MAP_SIZE / 10.0;
     let x = if pos.x() < map_10p {
-        Some(conf::prey::MAX_SPEED)
+        Some(species.max_speed)
     } else if pos.x() > conf::MAP_SIZE - map_10p {
-        Some(-conf::prey::MAX_SPEED)
+        Some(-species.max_speed)
     } else {
         None
     };
     let y = if pos.y() < map_10p {
-        Some(conf::prey::MAX_SPEED)
+        Some(species.max_speed)
     } else if pos.y() > conf::MAP_SIZE - map_10p {
-        Some(-conf::prey::MAX_SPEED)
+        Some(-species.max_speed)
     } else {
         None
     };
@@ -186,3 +995,204 @@ fn wall_repelling_force(pos: Vec3) -> Option<Vec3> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conf::prey::alarm::*;
+    use rand::SeedableRng;
+
+    fn alarmed(level: f32) -> Alarm {
+        Alarm {
+            level,
+            dir: Vec3::unit_x(),
+        }
+    }
+
+    #[test]
+    fn alarm_fades_over_time() {
+        let alarms = spread(&[alarmed(1.0)], |_, _| true, 0.5, 0.1);
+        assert!((alarms[0].level - (1.0 - DECAY * 0.1)).abs() < 1e-6);
+
+        // The alarm never drops below zero.
+        let alarms = spread(&[alarmed(1.0)], |_, _| true, 0.5, 1e6);
+        assert_eq!(alarms[0].level, 0.0);
+        assert_eq!(alarms[0].dir, Vec3::unit_x());
+    }
+
+    #[test]
+    fn alarm_below_threshold_doesnt_spread() {
+        let weak = alarmed(THRESHOLD * 0.9);
+        let alarms = spread(&[weak, Alarm::default()], |_, _| true, 1.0, 0.0);
+
+        assert_eq!(alarms[1], Alarm::default());
+    }
+
+    #[test]
+    fn alarm_travels_one_hop_per_tick() {
+        // Each prey only sees its neighbours in a line.
+        let sees = |a: usize, b: usize| (a as isize - b as isize).abs() == 1;
+        let alarms = [alarmed(1.0), Alarm::default(), Alarm::default()];
+
+        let alarms = spread(&alarms, sees, 0.5, 0.0);
+        assert_eq!(alarms[1], alarmed(0.5));
+        assert_eq!(alarms[2], Alarm::default());
+
+        let alarms = spread(&alarms, sees, 0.5, 0.0);
+        assert_eq!(alarms[2], alarmed(0.25));
+    }
+
+    #[test]
+    fn unseen_alarm_doesnt_spread() {
+        let alarms = [alarmed(1.0), Alarm::default()];
+        let alarms = spread(&alarms, |_, _| false, 0.5, 0.0);
+
+        assert_eq!(alarms[1], Alarm::default());
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn flees_away_from_predator() {
+        let prey = Vec3::new(100.0, 0.0, 0.0);
+        let vel = Vec3::new(10.0, 10.0, 0.0);
+        let dir = escape_direction(Escape::Away, prey, Vec3::zero(), vel);
+
+        assert_close(dir, Vec3::unit_x());
+    }
+
+    #[test]
+    fn flees_away_from_interception_point() {
+        let prey = Vec3::new(100.0, 0.0, 0.0);
+        // The predator's course gets closest to the prey at (50, 50).
+        let vel = Vec3::new(10.0, 10.0, 0.0);
+        let dir = escape_direction(Escape::Predictive, prey, Vec3::zero(), vel);
+
+        assert_close(dir, Vec3::new(1.0, -1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn flees_away_from_receding_or_still_predator() {
+        let prey = Vec3::new(100.0, 0.0, 0.0);
+        let receding = Vec3::new(-10.0, 5.0, 0.0);
+        let dir =
+            escape_direction(Escape::Predictive, prey, Vec3::zero(), receding);
+        assert_close(dir, Vec3::unit_x());
+
+        let dir = escape_direction(
+            Escape::Predictive,
+            prey,
+            Vec3::zero(),
+            Vec3::zero(),
+        );
+        assert_close(dir, Vec3::unit_x());
+    }
+
+    #[test]
+    fn dodges_predator_heading_right_at_it() {
+        let prey = Vec3::new(100.0, 0.0, 0.0);
+        let vel = Vec3::new(10.0, 0.0, 0.0);
+        let dir = escape_direction(Escape::Predictive, prey, Vec3::zero(), vel);
+
+        // Dodges to the left of the predator's course.
+        assert_close(dir, Vec3::unit_y());
+    }
+
+    #[test]
+    fn respawns_away_from_predators() {
+        let mut rng = StdRng::seed_from_u64(conf::SEED);
+        let center = conf::MAP_SIZE / 2.0;
+        let predators = [Vec3::new(center, center, 0.0)];
+        let policy = Respawn::AwayFromPredators {
+            min_distance: 500.0,
+        };
+
+        for _ in 0..10 {
+            let pos = respawn_position(
+                policy,
+                &Species::default(),
+                &mut rng,
+                &predators,
+                &[],
+            )
+            .unwrap();
+            assert!(pos.distance2(predators[0]) >= 500.0);
+        }
+    }
+
+    #[test]
+    fn respawns_as_far_as_possible_if_predators_are_everywhere() {
+        let mut rng = StdRng::seed_from_u64(conf::SEED);
+        let predators = [Vec3::zero()];
+        // No place on the map is this far from the corner.
+        let policy = Respawn::AwayFromPredators {
+            min_distance: 2.0 * conf::MAP_SIZE,
+        };
+        let pos = respawn_position(
+            policy,
+            &Species::default(),
+            &mut rng,
+            &predators,
+            &[],
+        )
+        .unwrap();
+
+        // The farthest of the tried positions is surely in the far half of
+        // the map.
+        assert!(pos.x() + pos.y() > conf::MAP_SIZE);
+    }
+
+    #[test]
+    fn rested_prey_runs_at_full_speed() {
+        let species = Species::default();
+        let mut stamina = Stamina::full(conf::prey::stamina::MAX);
+        assert_eq!(max_speed(&species, &stamina), species.max_speed);
+
+        // Prey runs at full speed until it drops below the fatigue threshold.
+        stamina.drain(
+            conf::prey::stamina::MAX
+                * (1.0 - conf::prey::stamina::FATIGUE_THRESHOLD),
+        );
+        assert_eq!(max_speed(&species, &stamina), species.max_speed);
+    }
+
+    #[test]
+    fn exhausted_prey_slows_down() {
+        let species = Species::default();
+        let mut stamina = Stamina::full(conf::prey::stamina::MAX);
+        stamina.drain(conf::prey::stamina::MAX);
+
+        let expected =
+            conf::prey::stamina::EXHAUSTED_SPEED.min(species.max_speed);
+        assert_eq!(max_speed(&species, &stamina), expected);
+    }
+
+    #[test]
+    fn fleeing_prey_gets_tired() {
+        let species = Species::default();
+        let mut stamina = Stamina::full(conf::prey::stamina::MAX);
+        tire(&mut stamina, &species, species.max_speed, true, 1.0);
+
+        // Running at max speed drains one unit of stamina per second.
+        assert!(
+            (stamina.current() - (conf::prey::stamina::MAX - 1.0)).abs() < 1e-4
+        );
+    }
+
+    #[test]
+    fn prey_rests_unless_it_flees() {
+        let species = Species::default();
+        let mut stamina = Stamina::full(conf::prey::stamina::MAX);
+        stamina.drain(conf::prey::stamina::MAX);
+
+        tire(&mut stamina, &species, species.max_speed, false, 1.0);
+        assert_eq!(stamina.current(), conf::prey::stamina::REGENERATION);
+
+        // Prey doesn't get tired at cruising speed even when it flees.
+        let cruising = conf::prey::stamina::CRUISING_SPEED;
+        tire(&mut stamina, &species, cruising, true, 1.0);
+        assert_eq!(stamina.current(), 2.0 * conf::prey::stamina::REGENERATION);
+    }
+}
@@ -3,8 +3,9 @@
 //! * components
 //! * resources
 //!
-//! TODO: There's also net modules for UDP communication with actors that
-//! control predator entities.
+//! There's also net module for communication with actors that control
+//! predator entities.
+//! TODO: UDP communication.
 
 #[macro_use]
 extern crate shrinkwraprs;
@@ -12,6 +13,9 @@ extern crate shrinkwraprs;
 pub mod components;
 pub mod conf;
 mod entities;
+pub mod net;
+pub mod observation;
+pub mod params;
 mod prelude;
 pub mod resources;
 
@@ -24,32 +28,66 @@ fn main() {
         // expensive computation.
         .add_resource(resources::FlockUpdateTimer::default())
         .add_resource(resources::KeyPressDelay::default())
+        .add_resource(resources::Tick::default())
+        .add_resource(resources::Rng::default())
+        .add_resource(resources::Joins::default())
+        .add_resource(resources::PreyRespawns::default())
+        .add_resource(resources::Metrics::default())
+        .add_resource(resources::TeamScores::default())
+        // Actors who want to join the game are waiting here.
+        .add_resource(net::Lobby::default())
+        // Trainers who want to receive the global state subscribe here.
+        .add_resource(net::trainer::Trainers::default())
         .add_default_plugins()
         .add_startup_system(components::camera::new.system())
         .add_startup_system(components::walls::new.system())
+        .add_startup_system(components::obstacles::new.system())
         .add_startup_system(entities::predator::init.system())
         .add_startup_system(entities::prey::init.system())
+        // Spawns predators and prey for actors who joined since the last tick.
+        .add_system(entities::join.system())
         // Must be called before any state updates.
+        .add_system(resources::advance_tick.system())
         .add_system(entities::predator::reset_world_view.system())
         // Simulates interactions between prey and predators.
         .add_system(entities::interact.system())
+        // Startled prey alarms its flockmates.
+        .add_system(entities::prey::spread_alarm.system())
         // Simulates flocking behavior for prey which isn't in danger. We should
         // run the logic which lets prey spot a predator before this system to
         // avoid needless computation.
         .add_system(entities::prey::flocking_behavior.system())
+        // Tires out prey which runs too fast.
+        .add_system(entities::prey::fatigue.system())
+        .add_system(entities::prey::respawn_delayed.system())
+        // Prey reproduces in ecology mode.
+        .add_system(entities::prey::reproduce.system())
+        .add_system(entities::prey::count_population.system())
         // Find hot single predators in your area.
         .add_system(entities::predator::find_nearby_predators.system());
 
     #[cfg(feature = "keyboard-control")]
     app.add_system(entities::predator::keyboard_movement.system());
 
+    // Applies actions which actors sent over the network.
+    app.add_system(entities::predator::network_movement.system())
+        .add_system(entities::prey::network_movement.system())
+        // Ends lunges and regenerates predators' stamina.
+        .add_system(entities::predator::recover.system())
+        // Kills predators which ran out of energy.
+        .add_system(entities::predator::starve.system());
+
     // Allows to change camera focus
     app.add_system(entities::predator::change_camera_focus.system())
         // Moves all entities along their velocity vectors.
         .add_system(entities::nudge.system())
         // Allows for zooming of camera and following focused predator.
         .add_system(components::camera::zoom.system())
-        .add_system(components::camera::follow.system());
+        .add_system(components::camera::follow.system())
+        // Must be called after all state updates.
+        .add_system(entities::predator::send_observations.system())
+        .add_system(entities::prey::send_observations.system())
+        .add_system(net::trainer::broadcast_global_state.system());
 
     app.run();
 }
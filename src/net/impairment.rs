@@ -0,0 +1,85 @@
+//! Policies trained on a perfect network break down in real deployments. The
+//! impairment layer sits between the channels and the game and delays, drops
+//! and reorders messages. Time is measured in ticks and every link has its own
+//! seeded RNG, so degraded runs are reproducible.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// How badly is the network impaired. The default is a perfect network.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Impairment {
+    /// Every message is delayed by this many ticks.
+    pub latency: u64,
+    /// Every message is further delayed by random number of ticks up to
+    /// this value.
+    pub jitter: u64,
+    /// Probability that a message is lost.
+    pub loss: f32,
+    /// Probability that a message is held back so that the messages sent
+    /// after it overtake it.
+    pub reorder: f32,
+}
+
+/// One direction of communication between the game and an actor.
+pub struct Link<T> {
+    impairment: Impairment,
+    rng: StdRng,
+    // Messages which haven't been delivered yet with the tick at which they
+    // arrive and the order in which they were sent.
+    in_flight: Vec<(u64, u64, T)>,
+    sent: u64,
+}
+
+impl<T> Link<T> {
+    pub fn new(impairment: Impairment, seed: u64) -> Self {
+        Self {
+            impairment,
+            rng: StdRng::seed_from_u64(seed),
+            in_flight: Vec::new(),
+            sent: 0,
+        }
+    }
+
+    /// Sends a message at given tick. It might never arrive.
+    pub fn send(&mut self, now: u64, msg: T) {
+        let Impairment {
+            latency,
+            jitter,
+            loss,
+            reorder,
+        } = self.impairment;
+        if self.rng.gen::<f32>() < loss {
+            return;
+        }
+
+        let mut arrives_at = now + latency + self.rng.gen_range(0, jitter + 1);
+        if self.rng.gen::<f32>() < reorder {
+            // Holds the message for at least one tick so that it's overtaken.
+            arrives_at += self.rng.gen_range(1, jitter + 2);
+        }
+
+        self.in_flight.push((arrives_at, self.sent, msg));
+        self.sent += 1;
+    }
+
+    /// Returns all messages which weren't lost, regardless of when they would
+    /// arrive.
+    pub fn flush(&mut self) -> Vec<T> {
+        self.receive(u64::MAX)
+    }
+
+    /// Returns messages which arrived by given tick in order of their arrival.
+    pub fn receive(&mut self, now: u64) -> Vec<T> {
+        self.in_flight
+            .sort_by_key(|(arrives_at, order, _)| (*arrives_at, *order));
+        let arrived = self
+            .in_flight
+            .iter()
+            .take_while(|(arrives_at, ..)| *arrives_at <= now)
+            .count();
+        self.in_flight
+            .drain(..arrived)
+            .map(|(.., msg)| msg)
+            .collect()
+    }
+}
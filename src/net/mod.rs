@@ -0,0 +1,383 @@
+//! Actors control predators over the network. The UDP socket listener
+//! translates datagrams into messages which are passed to the game over
+//! channels. This module contains the game side of those channels.
+//!
+//! An actor joins the game by sending a [`Join`] into the [`Lobby`]. On next
+//! tick a new predator or prey, depending on the actor's [`Role`], is spawned
+//! for them and from then on the actor sends [`Action`]s to steer it and
+//! receives an [`Update`] at the end of each tick, or every few ticks if the
+//! actor asked for action repeat.
+//!
+//! Trainers can additionally subscribe to the global state of the game, see
+//! [`trainer`].
+//!
+//! Actors can ask for the network to be impaired, see [`impairment`].
+//!
+//! TODO: UDP listener which feeds the lobby.
+
+pub mod impairment;
+pub mod trainer;
+
+pub use impairment::Impairment;
+
+use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
+use std::collections::VecDeque;
+
+use crate::{
+    components::Team,
+    observation::{Observation, ObservationKind},
+    prelude::*,
+};
+
+/// Which entity does an actor control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Role {
+    #[default]
+    Predator,
+    /// Prey controlled by an actor doesn't flock. It's rewarded for each tick
+    /// it survives, see [`conf::prey::actor`].
+    Prey,
+}
+
+/// Actors pick which kind of actions they are going to send when they join
+/// the game. They cannot change it afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ActionSpace {
+    /// Actor sends target velocity vectors.
+    #[default]
+    Continuous,
+    /// Actor sends one of [`DiscreteAction`]s, same as the keyboard.
+    Discrete,
+}
+
+/// Discrete actions mirror the keys which steer the keyboard controlled
+/// predator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiscreteAction {
+    Noop,
+    TurnLeft,
+    TurnRight,
+    Accelerate,
+    Brake,
+}
+
+/// A message which teammates send each other, see
+/// [`conf::predator::communication`].
+pub type Message = [f32; conf::predator::communication::MESSAGE_SIZE];
+
+/// What an actor wants its predator to do. The predator keeps doing the last
+/// action until a new one arrives, except for lunge which is done once.
+/// Continuous and discrete actions optionally carry a message which the
+/// predator broadcasts to teammates nearby once, when the action arrives.
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    /// Velocity the predator should move with. It's clamped by predator's max
+    /// speed.
+    Continuous(Vec3, Option<Message>),
+    Discrete(DiscreteAction, Option<Message>),
+    /// Predator briefly exceeds its max speed at the cost of stamina. This
+    /// action is available in both action spaces.
+    Lunge,
+}
+
+/// Settings an actor picks when they join the game.
+#[derive(Clone, Copy, Debug)]
+pub struct JoinSettings {
+    pub role: Role,
+    pub action_space: ActionSpace,
+    pub observation: ObservationKind,
+    /// For how many ticks is each action applied. The actor only receives an
+    /// update every this many ticks. Must be at least 1.
+    pub action_repeat: usize,
+    /// How many last frames are stacked in each update. Must be at least 1.
+    pub stack: usize,
+    /// Applied to both actions and updates.
+    pub impairment: Impairment,
+    /// Team the actor's predator hunts with. Ignored for prey.
+    pub team: Team,
+}
+
+/// What an actor receives after their predator acted.
+#[derive(Clone, Debug)]
+pub struct Update {
+    /// Sum of rewards the predator received since the last update.
+    pub reward: f32,
+    /// Exactly as many frames as the actor asked for when they joined,
+    /// ordered from the oldest to the latest. Frames are made each time an
+    /// update is sent. Until there are enough of them, the oldest frame is
+    /// repeated.
+    pub frames: Vec<Frame>,
+    /// The predator starved or the prey was eaten and this is the last update
+    /// the actor receives.
+    pub terminal: bool,
+}
+
+/// What the actor's entity saw at the end of a tick.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Tick at the end of which the observation was made.
+    pub tick: u64,
+    /// Entity's own velocity.
+    pub vel: Vec3,
+    /// How much stamina the entity has left as a fraction of the max.
+    /// Predators spend it on lunges, prey on running fast.
+    pub stamina: f32,
+    /// How much energy the predator has left as a fraction of the max. Prey
+    /// doesn't get hungry, so it's always 1 for prey.
+    pub energy: f32,
+    pub observation: Observation,
+    /// Messages heard from teammates nearby during the tick, ordered from the
+    /// nearest sender. Like the observation, messages heard on ticks without
+    /// an update are not sent.
+    pub messages: Vec<Message>,
+}
+
+/// A request of an actor to join the game. The listener keeps the other ends
+/// of the channels to forward actor's messages.
+pub struct Join {
+    pub settings: JoinSettings,
+    pub actions: Receiver<Action>,
+    pub updates: Sender<Update>,
+}
+
+/// Holds join requests which haven't been processed by the game yet.
+pub struct Lobby {
+    tx: Sender<Join>,
+    rx: Receiver<Join>,
+}
+
+/// Attached to predators and prey which are controlled over the network.
+pub struct Actor {
+    settings: JoinSettings,
+    actions: Receiver<Action>,
+    updates: Sender<Update>,
+    // Actions and updates pass through the impaired links before they are
+    // delivered.
+    action_link: impairment::Link<Action>,
+    update_link: impairment::Link<Update>,
+    last_action: Option<Action>,
+    // Whether the actor asked for a lunge since it was last taken.
+    lunge: bool,
+    // The latest message the actor sent since it was last taken.
+    message: Option<Message>,
+    // How many ticks passed since the last update was sent.
+    ticks_since_update: usize,
+    // Reward accumulated since the last update was sent.
+    reward: f32,
+    // Last frames sent to the actor, the latest at the back.
+    frames: VecDeque<Frame>,
+    // The actor has hung up and the predator should leave the game.
+    disconnected: bool,
+}
+
+impl Default for JoinSettings {
+    fn default() -> Self {
+        Self {
+            role: Role::default(),
+            action_space: ActionSpace::default(),
+            observation: ObservationKind::default(),
+            action_repeat: 1,
+            stack: 1,
+            impairment: Impairment::default(),
+            team: Team::default(),
+        }
+    }
+}
+
+impl DiscreteAction {
+    /// Agents such as DQN pick an index of an action, so we support this
+    /// encoding in addition to the enum.
+    pub fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Self::Noop),
+            1 => Some(Self::TurnLeft),
+            2 => Some(Self::TurnRight),
+            3 => Some(Self::Accelerate),
+            4 => Some(Self::Brake),
+            _ => None,
+        }
+    }
+}
+
+impl Action {
+    /// Which action space does this action belong to. Returns None for
+    /// actions which belong to every action space.
+    pub fn space(&self) -> Option<ActionSpace> {
+        match self {
+            Self::Continuous(..) => Some(ActionSpace::Continuous),
+            Self::Discrete(..) => Some(ActionSpace::Discrete),
+            Self::Lunge => None,
+        }
+    }
+
+    /// Message the action carries, if any.
+    pub fn message(&self) -> Option<Message> {
+        match self {
+            Self::Continuous(_, message) | Self::Discrete(_, message) => {
+                *message
+            }
+            Self::Lunge => None,
+        }
+    }
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        let (tx, rx) = unbounded();
+        Self { tx, rx }
+    }
+}
+
+impl Lobby {
+    /// Returns a handle which the listener uses to send join requests.
+    pub fn handle(&self) -> Sender<Join> {
+        self.tx.clone()
+    }
+
+    /// Returns next join request if there's any.
+    pub fn next(&self) -> Option<Join> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl Actor {
+    /// Creates a new actor whose impaired links are seeded with given seed.
+    pub fn new(join: Join, seed: u64) -> Self {
+        let impairment = join.settings.impairment;
+        Self {
+            settings: join.settings,
+            actions: join.actions,
+            updates: join.updates,
+            action_link: impairment::Link::new(impairment, seed),
+            update_link: impairment::Link::new(
+                impairment,
+                seed.wrapping_add(1),
+            ),
+            last_action: None,
+            lunge: false,
+            message: None,
+            ticks_since_update: 0,
+            reward: 0.0,
+            frames: VecDeque::with_capacity(join.settings.stack),
+            disconnected: false,
+        }
+    }
+
+    pub fn settings(&self) -> &JoinSettings {
+        &self.settings
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    /// Drains all actions the actor sent since the last tick and returns the
+    /// most recent one which arrived through the impaired link by given tick.
+    /// Actions which don't belong to the actor's action space are ignored. If
+    /// no action arrived, the last action is repeated. Lunges are not returned
+    /// and messages are not repeated, see [`Actor::take_lunge`] and
+    /// [`Actor::take_message`].
+    pub fn receive_action(&mut self, tick: u64) -> Option<Action> {
+        loop {
+            match self.actions.try_recv() {
+                Ok(action) => self.action_link.send(tick, action),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                }
+            }
+        }
+
+        for action in self.action_link.receive(tick) {
+            match (action, action.space()) {
+                (Action::Lunge, _) => self.lunge = true,
+                (_, Some(space)) if space == self.settings.action_space => {
+                    if let Some(message) = action.message() {
+                        self.message = Some(message);
+                    }
+                    self.last_action = Some(action);
+                }
+                // Actions from another action space are dropped silently,
+                // an actor which mixes them would otherwise flood the log.
+                (_, Some(_)) => (),
+                // Only lunges belong to every action space.
+                (_, None) => (),
+            }
+        }
+
+        self.last_action
+    }
+
+    /// Returns whether the actor asked for a lunge since the last call.
+    pub fn take_lunge(&mut self) -> bool {
+        std::mem::replace(&mut self.lunge, false)
+    }
+
+    /// Returns the latest message the actor sent since the last call.
+    pub fn take_message(&mut self) -> Option<Message> {
+        self.message.take()
+    }
+
+    /// Adds reward the predator received during a tick and returns whether
+    /// the actor should be sent an update at the end of this tick.
+    pub fn end_tick(&mut self, reward: f32) -> bool {
+        self.reward += reward;
+        self.ticks_since_update += 1;
+        self.ticks_since_update >= self.settings.action_repeat.max(1)
+    }
+
+    /// Stacks the frame with the previous ones and sends them together with
+    /// the accumulated reward to the actor through the impaired link.
+    ///
+    /// Terminal update is sent right away after all updates still in flight,
+    /// because the predator leaves the game and nothing would deliver it
+    /// later.
+    pub fn send_update(&mut self, frame: Frame, terminal: bool) {
+        let tick = frame.tick;
+        let stack = self.settings.stack.max(1);
+        if self.frames.len() == stack {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+
+        let mut frames = Vec::with_capacity(stack);
+        if let Some(oldest) = self.frames.front() {
+            for _ in self.frames.len()..stack {
+                frames.push(oldest.clone());
+            }
+        }
+        frames.extend(self.frames.iter().cloned());
+
+        let update = Update {
+            reward: self.reward,
+            frames,
+            terminal,
+        };
+        self.reward = 0.0;
+        self.ticks_since_update = 0;
+
+        if terminal {
+            let mut updates = self.update_link.flush();
+            updates.push(update);
+            self.deliver(updates);
+        } else {
+            self.update_link.send(tick, update);
+        }
+    }
+
+    /// Delivers updates which arrived through the impaired link by given
+    /// tick. If the actor hung up, they are marked as disconnected.
+    pub fn deliver_updates(&mut self, tick: u64) {
+        let updates = self.update_link.receive(tick);
+        self.deliver(updates);
+    }
+
+    fn deliver(&mut self, updates: Vec<Update>) {
+        for update in updates {
+            if self.updates.send(update).is_err() {
+                self.disconnected = true;
+            }
+        }
+    }
+}
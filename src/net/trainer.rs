@@ -0,0 +1,106 @@
+//! Centralized-training algorithms need the global state of the game next to
+//! each actor's partial observation. Trainers subscribe to the global state
+//! separately from actors, and the state is only assembled if there is at
+//! least one subscriber.
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::{
+    components::{Team, Velocity},
+    entities::{Predator, Prey},
+    prelude::*,
+};
+
+/// Position and velocity of every entity in the game at the end of a tick.
+#[derive(Clone, Debug)]
+pub struct GlobalState {
+    /// Seconds since the game started.
+    pub time: f64,
+    pub metrics: resources::Metrics,
+    /// Sum of rewards of each team which scored so far.
+    pub team_scores: Vec<(Team, f32)>,
+    pub prey: Vec<EntityState>,
+    pub predators: Vec<EntityState>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EntityState {
+    /// Identifies the entity across ticks.
+    pub id: u32,
+    pub pos: Vec3,
+    pub vel: Vec3,
+    /// Name of the prey's species. None for predators.
+    pub species: Option<&'static str>,
+}
+
+/// Keeps track of trainers subscribed to the global state.
+pub struct Trainers {
+    tx: Sender<Sender<GlobalState>>,
+    rx: Receiver<Sender<GlobalState>>,
+    subscribers: Vec<Sender<GlobalState>>,
+}
+
+impl Default for Trainers {
+    fn default() -> Self {
+        let (tx, rx) = unbounded();
+        Self {
+            tx,
+            rx,
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl Trainers {
+    /// Returns a handle which the listener uses to subscribe trainers.
+    pub fn handle(&self) -> Sender<Sender<GlobalState>> {
+        self.tx.clone()
+    }
+}
+
+/// Sends the global state to all subscribed trainers. Trainers who hung up
+/// are unsubscribed. This method MUST be called at the end of each tick after
+/// all world updates happened.
+pub fn broadcast_global_state(
+    time: Res<Time>,
+    metrics: Res<resources::Metrics>,
+    team_scores: Res<resources::TeamScores>,
+    mut trainers: ResMut<Trainers>,
+    mut prey_query: Query<(Entity, &Prey, &Translation, &Velocity)>,
+    mut predator_query: Query<(Entity, &Predator, &Translation, &Velocity)>,
+) {
+    while let Ok(subscriber) = trainers.rx.try_recv() {
+        trainers.subscribers.push(subscriber);
+    }
+    if trainers.subscribers.is_empty() {
+        return;
+    }
+
+    let mut state = GlobalState {
+        time: time.seconds_since_startup,
+        metrics: *metrics,
+        team_scores: team_scores.to_vec(),
+        prey: Vec::new(),
+        predators: Vec::new(),
+    };
+    for (entity, prey, pos, vel) in &mut prey_query.iter() {
+        state.prey.push(EntityState {
+            id: entity.id(),
+            pos: **pos,
+            vel: **vel,
+            species: Some(prey.species().name),
+        });
+    }
+    for (entity, _, pos, vel) in &mut predator_query.iter() {
+        state.predators.push(EntityState {
+            id: entity.id(),
+            pos: **pos,
+            vel: **vel,
+            species: None,
+        });
+    }
+
+    trainers
+        .subscribers
+        .retain(|subscriber| subscriber.send(state.clone()).is_ok());
+}
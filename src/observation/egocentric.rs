@@ -0,0 +1,137 @@
+//! Egocentric encoding expresses the k nearest prey and predators in the
+//! predator's frame of reference: the x axis points in the direction the
+//! predator is heading and the y axis points to its left. Therefore the
+//! encoding is invariant to predator's position and rotation.
+//!
+//! The output is always of the same size. If the predator sees fewer than k
+//! entities, the rest of the slots are padded with zeros and marked as not
+//! present.
+
+use super::{Sighting, View};
+use crate::{components::Team, prelude::*};
+
+/// Fixed-size egocentric observation.
+#[derive(Clone, Debug)]
+pub struct Egocentric {
+    /// Own velocity in the predator's frame of reference.
+    pub vel: Vec2,
+    /// Exactly k nearest prey sorted by distance.
+    pub prey: Vec<Entry>,
+    /// Exactly k nearest predators sorted by distance.
+    pub predators: Vec<Entry>,
+}
+
+/// Relative position and velocity of a seen entity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Entry {
+    /// Presence mask. If false, the rest of the entry is padding.
+    pub present: bool,
+    /// Whether the entry is a predator from the same team. Always false for
+    /// prey.
+    pub friend: bool,
+    /// Position relative to the predator in units of its view radius.
+    pub pos: Vec2,
+    /// Velocity relative to the predator in units of
+    /// [`conf::prey::MAX_SPEED`].
+    pub vel: Vec2,
+}
+
+impl Egocentric {
+    pub fn new(view: &View, k_prey: usize, k_predators: usize) -> Self {
+        let frame = Frame::new(view);
+        Self {
+            vel: frame.rotate(view.vel) / conf::prey::MAX_SPEED,
+            prey: frame.nearest(view.nearby_prey, k_prey),
+            predators: frame.nearest(view.nearby_predators, k_predators),
+        }
+    }
+
+    /// Flattens the observation into a vector of floats. Each entry is
+    /// encoded as `[present, friend, x, y, vx, vy]`. The length of the vector
+    /// only depends on k.
+    pub fn to_vec(&self) -> Vec<f32> {
+        let entries = self.prey.iter().chain(self.predators.iter());
+        let mut out = Vec::with_capacity(2 + 6 * entries.clone().count());
+        out.push(self.vel.x());
+        out.push(self.vel.y());
+        for entry in entries {
+            out.push(if entry.present { 1.0 } else { 0.0 });
+            out.push(if entry.friend { 1.0 } else { 0.0 });
+            out.push(entry.pos.x());
+            out.push(entry.pos.y());
+            out.push(entry.vel.x());
+            out.push(entry.vel.y());
+        }
+        out
+    }
+}
+
+/// Translates and rotates vectors into the predator's frame of reference.
+pub(super) struct Frame {
+    pos: Vec3,
+    vel: Vec3,
+    heading: Vec3,
+    team: Option<Team>,
+    radius: f32,
+}
+
+impl Frame {
+    pub(super) fn new(view: &View) -> Self {
+        Self {
+            pos: view.pos,
+            vel: view.vel,
+            heading: view.heading,
+            team: view.team,
+            radius: view.radius,
+        }
+    }
+
+    /// Projects the vector onto the heading and onto the vector perpendicular
+    /// to the heading which points to the left.
+    pub(super) fn rotate(&self, v: Vec3) -> Vec2 {
+        Vec2::new(v.dot(self.heading), v.dot(self.left()))
+    }
+
+    /// Translates the vector from the predator's frame of reference back to
+    /// the world.
+    pub(super) fn to_world(&self, v: Vec2) -> Vec3 {
+        self.pos + self.heading * v.x() + self.left() * v.y()
+    }
+
+    /// Converts world position into the predator's frame of reference.
+    pub(super) fn to_local(&self, pos: Vec3) -> Vec2 {
+        self.rotate(pos - self.pos)
+    }
+
+    fn left(&self) -> Vec3 {
+        Vec3::new(-self.heading.y(), self.heading.x(), 0.0)
+    }
+
+    fn entry(&self, sighting: &Sighting) -> Entry {
+        Entry {
+            present: true,
+            friend: self.team.is_some() && sighting.team == self.team,
+            pos: self.to_local(sighting.pos) / self.radius,
+            vel: self.rotate(sighting.vel - self.vel) / conf::prey::MAX_SPEED,
+        }
+    }
+
+    // Picks k nearest sightings and pads the rest.
+    fn nearest(&self, sightings: &[Sighting], k: usize) -> Vec<Entry> {
+        let mut sorted: Vec<_> = sightings
+            .iter()
+            .map(|s| (s.pos.distance2(self.pos), s))
+            .collect();
+        sorted.sort_by(|(a, _), (b, _)| {
+            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut entries: Vec<_> = sorted
+            .into_iter()
+            .take(k)
+            .map(|(_, s)| self.entry(s))
+            .collect();
+        entries.resize(k, Entry::default());
+        entries
+    }
+}
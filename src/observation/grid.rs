@@ -0,0 +1,116 @@
+//! Grid encoding rasterizes the square around the predator with side of twice
+//! its view radius into an image-like input for convolutional policies. The
+//! grid is rotated so that the predator always faces along the x axis, ie.
+//! towards the last column. It's rendered on CPU so it doesn't depend on any
+//! window.
+
+use super::{egocentric::Frame, View};
+use crate::prelude::*;
+
+/// Each channel is a separate layer of the grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// How many prey are in the cell.
+    Prey = 0,
+    /// How many predators from the same team are in the cell.
+    Teammates = 1,
+    /// How many predators from other teams are in the cell.
+    Opponents = 2,
+    /// 1 if the cell is blocked by a wall or an obstacle, 0 otherwise.
+    Blocked = 3,
+}
+
+/// How many channels does the grid have.
+pub const CHANNELS: usize = 4;
+
+/// Occupancy grid of size × size cells in each of [`CHANNELS`] channels.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub size: usize,
+    /// Cells in channel, row, column order. Rows go along the y axis from the
+    /// predator's right to its left, columns go along the x axis from behind
+    /// the predator to ahead of it.
+    pub cells: Vec<f32>,
+}
+
+impl Grid {
+    pub fn new(view: &View, size: usize) -> Self {
+        let mut grid = Self {
+            size,
+            cells: vec![0.0; CHANNELS * size * size],
+        };
+        let frame = Frame::new(view);
+        let radius = view.radius;
+        let cell_size = 2.0 * radius / size as f32;
+
+        // Cell is blocked if its center is outside of the map or inside of
+        // an obstacle.
+        let map_size = conf::MAP_SIZE;
+        for row in 0..size {
+            for col in 0..size {
+                let local = Vec2::new(
+                    (col as f32 + 0.5) * cell_size - radius,
+                    (row as f32 + 0.5) * cell_size - radius,
+                );
+                let world = frame.to_world(local);
+                let outside_map = world.x() < 0.0
+                    || world.y() < 0.0
+                    || world.x() > map_size
+                    || world.y() > map_size;
+                let in_obstacle = view.obstacles.iter().any(|b| {
+                    world.x() >= b.min.x()
+                        && world.x() <= b.max.x()
+                        && world.y() >= b.min.y()
+                        && world.y() <= b.max.y()
+                });
+                if outside_map || in_obstacle {
+                    *grid.cell_mut(Channel::Blocked, row, col) = 1.0;
+                }
+            }
+        }
+
+        let mut count = |channel, pos| {
+            let local = frame.to_local(pos);
+            let col = ((local.x() + radius) / cell_size).floor();
+            let row = ((local.y() + radius) / cell_size).floor();
+            if col >= 0.0 && row >= 0.0 {
+                let (row, col) = (row as usize, col as usize);
+                if row < size && col < size {
+                    *grid.cell_mut(channel, row, col) += 1.0;
+                }
+            }
+        };
+        for prey in view.nearby_prey {
+            count(Channel::Prey, prey.pos);
+        }
+        for predator in view.nearby_predators {
+            let channel = if view.is_friend(predator) {
+                Channel::Teammates
+            } else {
+                Channel::Opponents
+            };
+            count(channel, predator.pos);
+        }
+
+        grid
+    }
+
+    /// Returns value of a cell.
+    pub fn cell(&self, channel: Channel, row: usize, col: usize) -> f32 {
+        self.cells[self.index(channel, row, col)]
+    }
+
+    fn cell_mut(
+        &mut self,
+        channel: Channel,
+        row: usize,
+        col: usize,
+    ) -> &mut f32 {
+        let index = self.index(channel, row, col);
+        &mut self.cells[index]
+    }
+
+    fn index(&self, channel: Channel, row: usize, col: usize) -> usize {
+        (channel as usize * self.size + row) * self.size + col
+    }
+}
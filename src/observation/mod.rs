@@ -0,0 +1,168 @@
+//! Observations are sent to actors at the end of each tick. They describe what
+//! the actor's predator or prey sees. Each actor picks the encoding of
+//! observations when they join the game.
+
+pub mod egocentric;
+pub mod grid;
+pub mod raycast;
+
+pub use egocentric::Egocentric;
+pub use grid::Grid;
+pub use raycast::RayHit;
+
+use crate::{
+    components::{obstacles::Bounds, Team},
+    entities::Predator,
+    prelude::*,
+};
+
+/// Which encoding of observations does an actor receive.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ObservationKind {
+    /// Absolute positions and velocities of everything the predator sees.
+    #[default]
+    Raw,
+    /// Positions and velocities relative to the predator. See [`Egocentric`].
+    Egocentric {
+        /// How many nearest prey are encoded.
+        prey: usize,
+        /// How many nearest predators are encoded.
+        predators: usize,
+    },
+    /// Distances to the first thing hit by rays. See [`raycast`].
+    Raycast {
+        /// How many rays are cast.
+        rays: usize,
+        /// Angle in radians across which the rays are spread.
+        fov: f32,
+    },
+    /// Occupancy grid of the predator's surroundings. See [`grid`].
+    Grid {
+        /// How many cells are along each side of the grid.
+        size: usize,
+    },
+}
+
+/// Position and velocity of an entity as seen by a predator or prey.
+#[derive(Clone, Copy, Debug)]
+pub struct Sighting {
+    pub pos: Vec3,
+    pub vel: Vec3,
+    /// Team of a seen predator. None for prey.
+    pub team: Option<Team>,
+}
+
+/// What an actor receives at the end of each tick.
+#[derive(Clone, Debug)]
+pub enum Observation {
+    Raw {
+        pos: Vec3,
+        vel: Vec3,
+        /// None for prey.
+        team: Option<Team>,
+        nearby_prey: Vec<Sighting>,
+        nearby_predators: Vec<Sighting>,
+    },
+    Egocentric(Egocentric),
+    Raycast(Vec<RayHit>),
+    Grid(Grid),
+}
+
+/// Everything a predator or prey knows about itself and the world at the end
+/// of a tick.
+pub struct View<'a> {
+    pub nearby_prey: &'a [Sighting],
+    pub nearby_predators: &'a [Sighting],
+    /// Team of the predator. None for prey.
+    pub team: Option<Team>,
+    /// How far does the entity see.
+    pub radius: f32,
+    pub pos: Vec3,
+    pub vel: Vec3,
+    /// Unit vector in the direction the entity is facing.
+    pub heading: Vec3,
+    /// Boxes occupied by all obstacles in the map.
+    pub obstacles: &'a [Bounds],
+}
+
+impl ObservationKind {
+    /// Encodes the predator's view of the world.
+    pub fn encode(self, view: &View) -> Observation {
+        match self {
+            Self::Raw => Observation::Raw {
+                pos: view.pos,
+                vel: view.vel,
+                team: view.team,
+                nearby_prey: view.nearby_prey.to_vec(),
+                nearby_predators: view.nearby_predators.to_vec(),
+            },
+            Self::Egocentric { prey, predators } => {
+                Observation::Egocentric(Egocentric::new(view, prey, predators))
+            }
+            Self::Raycast { rays, fov } => {
+                Observation::Raycast(raycast::cast(view, rays, fov))
+            }
+            Self::Grid { size } => Observation::Grid(Grid::new(view, size)),
+        }
+    }
+}
+
+impl<'a> View<'a> {
+    pub fn new(
+        predator: &'a Predator,
+        team: Team,
+        pos: Vec3,
+        vel: Vec3,
+        rot: &Rotation,
+        obstacles: &'a [Bounds],
+    ) -> Self {
+        Self {
+            nearby_prey: predator.nearby_prey(),
+            nearby_predators: predator.nearby_predators(),
+            team: Some(team),
+            radius: conf::predator::VIEW_RADIUS,
+            pos,
+            vel,
+            heading: heading(vel, rot),
+            obstacles,
+        }
+    }
+
+    /// View of a prey which sees given prey and predators within given
+    /// radius.
+    pub fn of_prey(
+        nearby_prey: &'a [Sighting],
+        nearby_predators: &'a [Sighting],
+        radius: f32,
+        pos: Vec3,
+        vel: Vec3,
+        rot: &Rotation,
+        obstacles: &'a [Bounds],
+    ) -> Self {
+        Self {
+            nearby_prey,
+            nearby_predators,
+            team: None,
+            radius,
+            pos,
+            vel,
+            heading: heading(vel, rot),
+            obstacles,
+        }
+    }
+
+    /// Whether the seen predator is in the same team. Prey has no friends.
+    pub fn is_friend(&self, sighting: &Sighting) -> bool {
+        self.team.is_some() && sighting.team == self.team
+    }
+}
+
+// Entities face in the direction of their velocity. If they don't move, we
+// fall back to their last rotation.
+fn heading(vel: Vec3, rot: &Rotation) -> Vec3 {
+    if vel.is_zero() {
+        rot.mul_vec3(Vec3::unit_x())
+    } else {
+        vel.normalize()
+    }
+}
@@ -0,0 +1,300 @@
+//! Raycast encoding is the standard sensor setup in pursuit-evasion
+//! literature. The predator casts rays evenly spread across its field of view
+//! and for each ray reports the distance to and the type of the first thing
+//! the ray hits. Rays are as long as the predator's view radius.
+
+use super::View;
+use crate::{components::obstacles::Bounds, prelude::*};
+
+/// What did a ray hit first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hit {
+    Nothing,
+    Prey,
+    Teammate,
+    Opponent,
+    Wall,
+    Obstacle,
+}
+
+/// Result of a single ray.
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    /// Distance to the hit in units of the view radius. If nothing was hit,
+    /// it's 1.
+    pub distance: f32,
+    pub hit: Hit,
+}
+
+/// Casts `rays` rays spread across `fov` radians centered around predator's
+/// heading. Rays are ordered from right to left.
+pub fn cast(view: &View, rays: usize, fov: f32) -> Vec<RayHit> {
+    let step = if rays > 1 {
+        fov / (rays - 1) as f32
+    } else {
+        0.0
+    };
+    let first = if rays > 1 { -fov / 2.0 } else { 0.0 };
+
+    (0..rays)
+        .map(|i| {
+            let dir = view.heading.rotate(first + step * i as f32);
+            cast_ray(view, dir)
+        })
+        .collect()
+}
+
+/// Returns distance along the ray at which it enters a circle, if it does.
+/// If the origin is inside of the circle, the distance is zero.
+pub fn ray_circle(
+    origin: Vec3,
+    dir: Vec3,
+    center: Vec3,
+    r: f32,
+) -> Option<f32> {
+    let to_center = center - origin;
+    let sq_distance = to_center.length_squared();
+    if sq_distance <= r.powi(2) {
+        return Some(0.0);
+    }
+
+    let along = to_center.dot(dir);
+    let sq_off_ray = sq_distance - along.powi(2);
+    if along < 0.0 || sq_off_ray > r.powi(2) {
+        return None;
+    }
+
+    Some(along - (r.powi(2) - sq_off_ray).sqrt())
+}
+
+/// Returns distance along the ray at which it enters an axis aligned box, if
+/// it does. If the origin is inside of the box, the distance is zero.
+pub fn ray_box(origin: Vec3, dir: Vec3, bounds: &Bounds) -> Option<f32> {
+    let (enter, exit) = slabs(origin, dir, bounds)?;
+    if exit < 0.0 {
+        None
+    } else {
+        Some(enter.max(0.0))
+    }
+}
+
+/// Whether nothing blocks the segment between the observer and the target.
+/// Obstacles always block the line of sight. Bodies of the observer and of
+/// the target don't block it.
+pub fn line_of_sight(
+    from: Vec3,
+    to: Vec3,
+    obstacles: &[Bounds],
+    bodies: &[Vec3],
+) -> bool {
+    let offset = to - from;
+    let distance = offset.length();
+    if distance < f32::EPSILON {
+        return true;
+    }
+    let dir = offset / distance;
+    let blocks = |hit: Option<f32>| hit.is_some_and(|d| d < distance);
+
+    let r = conf::observation::BODY_RADIUS;
+    !obstacles
+        .iter()
+        .any(|bounds| blocks(ray_box(from, dir, bounds)))
+        && !bodies
+            .iter()
+            .filter(|body| body.distance2(from) > r && body.distance2(to) > r)
+            .any(|body| blocks(ray_circle(from, dir, *body, r)))
+}
+
+fn cast_ray(view: &View, dir: Vec3) -> RayHit {
+    let max = view.radius;
+    let mut closest = (max, Hit::Nothing);
+    let mut consider = |distance: Option<f32>, hit| {
+        if let Some(distance) = distance {
+            if distance < closest.0 {
+                closest = (distance, hit);
+            }
+        }
+    };
+
+    // The map is enclosed with walls, and the predator is always inside of
+    // it. Hence the ray always leaves the map.
+    let map = Bounds {
+        min: Vec2::zero(),
+        max: Vec2::splat(conf::MAP_SIZE),
+    };
+    consider(slabs(view.pos, dir, &map).map(|(_, exit)| exit), Hit::Wall);
+
+    for bounds in view.obstacles {
+        consider(ray_box(view.pos, dir, bounds), Hit::Obstacle);
+    }
+
+    let r = conf::observation::BODY_RADIUS;
+    for prey in view.nearby_prey {
+        consider(ray_circle(view.pos, dir, prey.pos, r), Hit::Prey);
+    }
+    for predator in view.nearby_predators {
+        let hit = if view.is_friend(predator) {
+            Hit::Teammate
+        } else {
+            Hit::Opponent
+        };
+        consider(ray_circle(view.pos, dir, predator.pos, r), hit);
+    }
+
+    let (distance, hit) = closest;
+    RayHit {
+        distance: distance / max,
+        hit,
+    }
+}
+
+// Distances along the ray at which it enters and exits the box. The ray might
+// never intersect the box, in which case returns None.
+fn slabs(origin: Vec3, dir: Vec3, bounds: &Bounds) -> Option<(f32, f32)> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let axes = [
+        (origin.x(), dir.x(), bounds.min.x(), bounds.max.x()),
+        (origin.y(), dir.y(), bounds.min.y(), bounds.max.y()),
+    ];
+    for &(o, d, min, max) in axes.iter() {
+        if d.abs() < f32::EPSILON {
+            // Parallel with the slab, must start within it.
+            if o < min || o > max {
+                return None;
+            }
+        } else {
+            let t1 = (min - o) / d;
+            let t2 = (max - o) / d;
+            enter = enter.max(t1.min(t2));
+            exit = exit.min(t1.max(t2));
+        }
+    }
+
+    if enter > exit {
+        None
+    } else {
+        Some((enter, exit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Bounds {
+        Bounds {
+            min: Vec2::new(10.0, -1.0),
+            max: Vec2::new(12.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn ray_enters_circle_in_front() {
+        let hit = ray_circle(
+            Vec3::zero(),
+            Vec3::unit_x(),
+            Vec3::new(10.0, 0.0, 0.0),
+            2.0,
+        );
+
+        assert_eq!(hit, Some(8.0));
+    }
+
+    #[test]
+    fn ray_misses_circle_behind_or_aside() {
+        let center = Vec3::new(-10.0, 0.0, 0.0);
+        assert_eq!(ray_circle(Vec3::zero(), Vec3::unit_x(), center, 2.0), None);
+
+        let center = Vec3::new(10.0, 3.0, 0.0);
+        assert_eq!(ray_circle(Vec3::zero(), Vec3::unit_x(), center, 2.0), None);
+    }
+
+    #[test]
+    fn ray_starts_inside_circle() {
+        let center = Vec3::new(1.0, 0.0, 0.0);
+        let hit = ray_circle(Vec3::zero(), -Vec3::unit_x(), center, 2.0);
+
+        assert_eq!(hit, Some(0.0));
+    }
+
+    #[test]
+    fn ray_enters_box_in_front() {
+        let hit = ray_box(Vec3::zero(), Vec3::unit_x(), &unit_box());
+
+        assert_eq!(hit, Some(10.0));
+    }
+
+    #[test]
+    fn ray_misses_box_behind_or_aside() {
+        let hit = ray_box(Vec3::zero(), -Vec3::unit_x(), &unit_box());
+        assert_eq!(hit, None);
+
+        let hit = ray_box(Vec3::zero(), Vec3::unit_y(), &unit_box());
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn ray_starts_inside_box() {
+        let origin = Vec3::new(11.0, 0.0, 0.0);
+        let hit = ray_box(origin, -Vec3::unit_x(), &unit_box());
+
+        assert_eq!(hit, Some(0.0));
+    }
+
+    #[test]
+    fn obstacle_blocks_line_of_sight() {
+        let from = Vec3::zero();
+
+        assert!(!line_of_sight(
+            from,
+            Vec3::new(20.0, 0.0, 0.0),
+            &[unit_box()],
+            &[]
+        ));
+        // The obstacle is behind the target or aside.
+        assert!(line_of_sight(
+            from,
+            Vec3::new(5.0, 0.0, 0.0),
+            &[unit_box()],
+            &[]
+        ));
+        assert!(line_of_sight(
+            from,
+            Vec3::new(20.0, 5.0, 0.0),
+            &[unit_box()],
+            &[]
+        ));
+    }
+
+    #[test]
+    fn body_blocks_line_of_sight() {
+        let from = Vec3::zero();
+        let to = Vec3::new(100.0, 0.0, 0.0);
+
+        assert!(!line_of_sight(from, to, &[], &[Vec3::new(50.0, 0.0, 0.0)]));
+        assert!(line_of_sight(from, to, &[], &[Vec3::new(50.0, 50.0, 0.0)]));
+        assert!(line_of_sight(from, to, &[], &[Vec3::new(150.0, 0.0, 0.0)]));
+    }
+
+    #[test]
+    fn own_bodies_dont_block_line_of_sight() {
+        let from = Vec3::zero();
+        let to = Vec3::new(100.0, 0.0, 0.0);
+
+        // Bodies of the observer and of the target are among the bodies.
+        assert!(line_of_sight(from, to, &[], &[from, to]));
+    }
+
+    #[test]
+    fn diagonal_ray_enters_box_corner() {
+        let dir = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let bounds = Bounds {
+            min: Vec2::new(3.0, 3.0),
+            max: Vec2::new(5.0, 5.0),
+        };
+        let hit = ray_box(Vec3::zero(), dir, &bounds).unwrap();
+
+        assert!((hit - 3.0 * 2f32.sqrt()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,107 @@
+//! Types of the game's parameters. Their values are set in [`conf`], which
+//! is why they don't depend on the entities they parametrize.
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::conf;
+
+/// Where and when does eaten prey come back to the game. The policy is set in
+/// [`conf::prey::RESPAWN`].
+#[derive(Clone, Copy, Debug)]
+pub enum Respawn {
+    /// Eaten prey never comes back and the population depletes.
+    Never,
+    /// Prey respawns anywhere on the map, possibly right next to a predator.
+    Random,
+    /// Prey respawns anywhere on the map at least this far from all
+    /// predators. If no such place is found, it respawns as far from them as
+    /// it can.
+    AwayFromPredators { min_distance: f32 },
+    /// Prey respawns close to an edge of the map.
+    MapEdge,
+    /// Prey respawns close to another randomly picked prey.
+    NearFlock,
+    /// Prey respawns anywhere on the map after this many seconds.
+    Delayed { seconds: f32 },
+}
+
+/// How does prey flee from predators it sees. Each species has its own policy,
+/// the common species uses [`conf::prey::ESCAPE`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Escape {
+    /// Prey flees directly away from each predator's current position.
+    Away,
+    /// Prey estimates when each predator's course gets closest to it and
+    /// flees away from that interception point. A prey right in the way of a
+    /// predator dodges to the side instead of running ahead of it.
+    Predictive,
+}
+
+/// Parameters of a prey species. Species which populate the map are listed in
+/// [`conf::prey::SPECIES`].
+#[derive(Clone, Copy, Debug)]
+pub struct Species {
+    pub name: &'static str,
+    /// Location of the sprite relative to the root.
+    pub icon: &'static str,
+    /// Size of the sprite relative to the icon.
+    pub scale: f32,
+    /// How likely is a new prey of this species relative to other species.
+    pub share: f32,
+    pub max_speed: f32,
+    pub min_speed: f32,
+    pub max_steering_force: f32,
+    pub view_radius: f32,
+    /// See [`conf::prey::VIEW_ANGLE`].
+    pub view_angle: f32,
+    /// See [`conf::prey::HEARING_RADIUS`].
+    pub hearing_radius: f32,
+    pub avoid_radius: f32,
+    pub escape: Escape,
+    pub weights: Weights,
+    /// Reward for eating the prey. Predators are fed this many meals.
+    pub value: f32,
+}
+
+/// How strongly is a prey steered by each of the forces.
+#[derive(Clone, Copy, Debug)]
+pub struct Weights {
+    pub wall_repelling: f32,
+    pub alignment: f32,
+    pub separation: f32,
+    pub cohesion: f32,
+    pub escape: f32,
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        conf::prey::COMMON
+    }
+}
+
+impl Species {
+    /// Picks a species from [`conf::prey::SPECIES`] at random in proportion
+    /// to their shares.
+    pub fn pick(rng: &mut StdRng) -> Self {
+        let species = conf::prey::SPECIES;
+        let total: f32 = species.iter().map(|s| s.share).sum();
+        let mut roll = rng.gen::<f32>() * total;
+        for s in species {
+            if roll < s.share {
+                return *s;
+            }
+            roll -= s.share;
+        }
+        species.last().copied().unwrap_or_default()
+    }
+}
+
+/// How is the reward for a captured prey divided among predators which are
+/// within strike radius. The rule is set in [`conf::predator::capture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Credit {
+    /// Each predator gets the full reward.
+    Full,
+    /// The reward is split evenly.
+    Split,
+}
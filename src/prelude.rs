@@ -2,8 +2,10 @@ pub use bevy::prelude::*;
 
 pub use crate::components;
 pub use crate::conf;
+pub use crate::net;
 pub use crate::resources;
 
+use rand::{rngs::StdRng, Rng};
 use std::error::Error;
 
 // /// This will work just fine for us, there isn't need for custom error.
@@ -11,12 +13,12 @@ use std::error::Error;
 pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
 pub trait InstantiateRandom {
-    fn random() -> Self;
+    fn random(rng: &mut StdRng) -> Self;
 }
 
 impl InstantiateRandom for Translation {
-    fn random() -> Self {
-        let rand_coord = || rand::random::<usize>() as f32 % conf::MAP_SIZE;
+    fn random(rng: &mut StdRng) -> Self {
+        let mut rand_coord = || rng.gen_range(0.0, conf::MAP_SIZE);
         Self::new(rand_coord(), rand_coord(), 0.0)
     }
 }
@@ -27,8 +29,13 @@ pub trait Vec3Ext {
     // Calculates euclidean distance in the xy plane.
     fn distance2(self, other: Self) -> f32;
 
-    // Creates a new perpendicular vector.
-    fn perpendicular(self) -> Self;
+    // Rotates the vector counter-clockwise by given angle in radians.
+    fn rotate(self, angle: f32) -> Self;
+
+    // Calculates the angle in radians by which this vector must be rotated
+    // counter-clockwise to point in the direction of the other one. The angle
+    // is in the range of [-PI, PI].
+    fn signed_angle(self, other: Self) -> f32;
 }
 
 impl Vec3Ext for Vec3 {
@@ -41,8 +48,17 @@ impl Vec3Ext for Vec3 {
         (self - other).length()
     }
 
-    fn perpendicular(self) -> Self {
-        // We don't care about z as we play in 2D.
-        Self::new(self.y(), self.x() * -1.0, 0.0)
+    fn rotate(self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(
+            self.x() * cos - self.y() * sin,
+            self.x() * sin + self.y() * cos,
+            0.0,
+        )
+    }
+
+    fn signed_angle(self, other: Self) -> f32 {
+        let cross = self.x() * other.y() - self.y() * other.x();
+        cross.atan2(self.x() * other.x() + self.y() * other.y())
     }
 }
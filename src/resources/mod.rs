@@ -1,6 +1,7 @@
-use std::time::Duration;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{collections::HashMap, time::Duration};
 
-use crate::prelude::*;
+use crate::{params::Species, prelude::*};
 
 /// Calculation of flocking behavior is expensive. We undergo this calculation
 /// only few times a second.
@@ -9,6 +10,43 @@ pub struct FlockUpdateTimer(Timer);
 /// Allows key to be pressed only once in a while. Prevents unwanted bursts.
 pub struct KeyPressDelay(Timer);
 
+/// Seeded random number generator which makes runs reproducible.
+#[derive(Shrinkwrap)]
+#[shrinkwrap(mutable)]
+pub struct Rng(pub StdRng);
+
+/// Counts actors who joined the game so far. Links of each actor are seeded
+/// from [`conf::SEED`] and the order in which the actor joined.
+#[derive(Default)]
+pub struct Joins(u64);
+
+/// Counts down seconds until each eaten prey respawns as the same species.
+/// Used with the delayed respawn policy.
+#[derive(Default)]
+pub struct PreyRespawns(Vec<(f32, Species)>);
+
+/// Counts events in the game which are interesting for analysis.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Metrics {
+    /// How many prey are in the game. Useful to track the population over
+    /// time in ecology mode.
+    pub prey_population: usize,
+    /// How many prey were born in ecology mode.
+    pub prey_born: u64,
+    /// How many prey were eaten.
+    pub prey_eaten: u64,
+    /// How many predators died of hunger.
+    pub predators_starved: u64,
+}
+
+/// Sum of rewards for captured prey per team.
+#[derive(Default, Clone, Debug)]
+pub struct TeamScores(HashMap<components::Team, f32>);
+
+/// Counts ticks since the game started.
+#[derive(Shrinkwrap, Default, Clone, Copy)]
+pub struct Tick(u64);
+
 impl FlockUpdateTimer {
     pub fn tick(&mut self, seconds: f32) {
         self.0.tick(seconds)
@@ -40,3 +78,60 @@ impl Default for KeyPressDelay {
         Self(Timer::new(Duration::from_millis(250), true))
     }
 }
+
+impl Joins {
+    /// Returns the seed for the links of the next actor who joins.
+    pub fn next_seed(&mut self) -> u64 {
+        self.0 += 1;
+        // Each actor has two links seeded with consecutive numbers.
+        conf::SEED.wrapping_add(2 * self.0)
+    }
+}
+
+impl PreyRespawns {
+    /// Schedules a new prey of given species to respawn after given number
+    /// of seconds.
+    pub fn schedule(&mut self, seconds: f32, species: Species) {
+        self.0.push((seconds, species));
+    }
+
+    /// Counts down and returns species of prey which should respawn now.
+    pub fn tick(&mut self, seconds: f32) -> Vec<Species> {
+        for (remaining, _) in self.0.iter_mut() {
+            *remaining -= seconds;
+        }
+        let (due, pending): (Vec<_>, Vec<_>) = self
+            .0
+            .drain(..)
+            .partition(|(remaining, _)| *remaining <= 0.0);
+        self.0 = pending;
+        due.into_iter().map(|(_, species)| species).collect()
+    }
+}
+
+impl TeamScores {
+    /// Adds a reward to the team's score.
+    pub fn add(&mut self, team: components::Team, reward: f32) {
+        *self.0.entry(team).or_insert(0.0) += reward;
+    }
+
+    /// Returns scores of all teams which scored so far.
+    pub fn to_vec(&self) -> Vec<(components::Team, f32)> {
+        let mut scores: Vec<_> =
+            self.0.iter().map(|(team, score)| (*team, *score)).collect();
+        scores.sort_by_key(|(team, _)| team.0);
+        scores
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(conf::SEED))
+    }
+}
+
+/// Advances the tick counter. This method MUST be called in the beginning of
+/// each tick.
+pub fn advance_tick(mut tick: ResMut<Tick>) {
+    tick.0 += 1;
+}